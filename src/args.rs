@@ -0,0 +1,71 @@
+use std::path::PathBuf;
+
+/// Song opened when none is given
+const DEFAULT_SONG: &str = "resources/I Built The Sky-Up Into the Ether.gp5";
+
+/// Command line: `rstabs [song.gp5] [--track N]... [--staves N] [--svg tab.svg]
+/// [--pdf tab.pdf] [--ly tab.ly] [--html tab.html]`. With an export option the song is written
+/// to the file instead of being opened.
+pub struct Args {
+    pub song: PathBuf,
+    /// Tracks to export, counted from 0. All of them in a PDF or a LilyPond file when empty,
+    /// the first one in an SVG or an HTML page.
+    pub tracks: Vec<usize>,
+    /// Most systems on a printed page
+    pub staves: Option<usize>,
+    pub svg: Option<PathBuf>,
+    pub pdf: Option<PathBuf>,
+    pub ly: Option<PathBuf>,
+    pub html: Option<PathBuf>,
+}
+
+impl Args {
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut song: Option<PathBuf> = None;
+        let mut result = Args {
+            song: PathBuf::new(),
+            tracks: Vec::new(),
+            staves: None,
+            svg: None,
+            pdf: None,
+            ly: None,
+            html: None,
+        };
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if !arg.starts_with("--") {
+                if song.replace(PathBuf::from(&arg)).is_some() {
+                    return Err(format!("unexpected argument {}", arg));
+                }
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| format!("{} needs a value", arg))?;
+            match arg.as_str() {
+                "--svg" => result.svg = Some(PathBuf::from(value)),
+                "--pdf" => result.pdf = Some(PathBuf::from(value)),
+                "--ly" => result.ly = Some(PathBuf::from(value)),
+                "--html" => result.html = Some(PathBuf::from(value)),
+                "--track" => result.tracks.push(count(&arg, &value)? - 1),
+                "--staves" => result.staves = Some(count(&arg, &value)?),
+                _ => return Err(format!("unknown option {}", arg)),
+            }
+        }
+        result.song = song.unwrap_or_else(|| PathBuf::from(DEFAULT_SONG));
+        Ok(result)
+    }
+
+    /// Whether the song is written to a file instead of being opened
+    pub fn is_export(&self) -> bool {
+        self.svg.is_some() || self.pdf.is_some() || self.ly.is_some() || self.html.is_some()
+    }
+}
+
+/// A number from 1
+fn count(arg: &str, value: &str) -> Result<usize, String> {
+    match value.parse::<usize>() {
+        Ok(num) if num >= 1 => Ok(num),
+        _ => Err(format!("{} needs a number from 1, not {}", arg, value)),
+    }
+}
@@ -0,0 +1,280 @@
+use console::Key;
+
+use crate::config::Entry;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    MoveUp,
+    MoveDown,
+    NextNote,
+    PreviousNote,
+    Start,
+    End,
+    HalfPageUp,
+    HalfPageDown,
+    PageUp,
+    PageDown,
+    GoToMeasure,
+    Markers,
+    ToggleNotation,
+    CycleTheme,
+    ChordPanel,
+    ToggleVoice1,
+    ToggleVoice2,
+    HighlightVoice,
+    ToggleLinear,
+    Play,
+    Loop,
+    ToggleEdit,
+    ToggleHammer,
+    ToggleSlideIn,
+    ToggleSlideOut,
+    ToggleTie,
+    ToggleGhostNote,
+    ToggleHarmonic,
+    ToggleDeadNote,
+    TogglePalmMute,
+    ToggleVibrato,
+    ToggleBend,
+    Shorter,
+    Longer,
+    Dot,
+    Tuplet,
+    Rest,
+    NextTrack,
+    PreviousTrack,
+    TrackMenu,
+    Help,
+    Cancel,
+    Confirm,
+    Quit,
+}
+
+/// Every action with its name in the config file and its default keys.
+const ACTIONS: [(Action, &str, &[&str]); 46] = [
+    (Action::MoveLeft, "move_left", &["h", "<Left>"]),
+    (Action::MoveRight, "move_right", &["l", "<Right>"]),
+    (Action::MoveUp, "move_up", &["k", "<Up>"]),
+    (Action::MoveDown, "move_down", &["j", "<Down>"]),
+    (Action::NextNote, "next_note", &["w"]),
+    (Action::PreviousNote, "previous_note", &["b"]),
+    (Action::Start, "start", &["gg"]),
+    (Action::End, "end", &["G"]),
+    (Action::HalfPageUp, "half_page_up", &["<C-u>"]),
+    (Action::HalfPageDown, "half_page_down", &["<C-d>"]),
+    (Action::PageUp, "page_up", &["<PageUp>"]),
+    (Action::PageDown, "page_down", &["<PageDown>"]),
+    (Action::GoToMeasure, "go_to_measure", &[":"]),
+    (Action::Markers, "markers", &["m"]),
+    (Action::ToggleNotation, "toggle_notation", &["n"]),
+    (Action::CycleTheme, "cycle_theme", &["c"]),
+    (Action::ChordPanel, "chord_panel", &["d"]),
+    (Action::ToggleVoice1, "toggle_voice_1", &["1"]),
+    (Action::ToggleVoice2, "toggle_voice_2", &["2"]),
+    (Action::HighlightVoice, "highlight_voice", &["v"]),
+    (Action::ToggleLinear, "toggle_linear", &["r"]),
+    (Action::Play, "play", &["p", "<Space>"]),
+    (Action::Loop, "loop", &["L"]),
+    (Action::ToggleEdit, "toggle_edit", &["e"]),
+    (Action::ToggleHammer, "toggle_hammer", &["H"]),
+    (Action::ToggleSlideIn, "toggle_slide_in", &["S"]),
+    (Action::ToggleSlideOut, "toggle_slide_out", &["s"]),
+    (Action::ToggleTie, "toggle_tie", &["-"]),
+    (Action::ToggleGhostNote, "toggle_ghost_note", &["("]),
+    (Action::ToggleHarmonic, "toggle_harmonic", &["*"]),
+    (Action::ToggleDeadNote, "toggle_dead_note", &["x"]),
+    (Action::TogglePalmMute, "toggle_palm_mute", &["M"]),
+    (Action::ToggleVibrato, "toggle_vibrato", &["~"]),
+    (Action::ToggleBend, "toggle_bend", &["B"]),
+    (Action::Shorter, "shorter", &["["]),
+    (Action::Longer, "longer", &["]"]),
+    (Action::Dot, "dot", &["."]),
+    (Action::Tuplet, "tuplet", &["3"]),
+    (Action::Rest, "rest", &["R"]),
+    (Action::NextTrack, "next_track", &["t"]),
+    (Action::PreviousTrack, "previous_track", &["T"]),
+    (Action::TrackMenu, "track_menu", &["<Tab>"]),
+    (Action::Help, "help", &["?"]),
+    (Action::Cancel, "cancel", &["<Esc>"]),
+    (Action::Confirm, "confirm", &["<Enter>"]),
+    (Action::Quit, "quit", &["q"]),
+];
+
+impl Action {
+    fn from_name(name: &str) -> Option<Self> {
+        ACTIONS
+            .iter()
+            .find(|(_, action_name, _)| *action_name == name)
+            .map(|(action, _, _)| *action)
+    }
+}
+
+/// Result of looking up the keys typed so far.
+pub enum Lookup {
+    Action(Action),
+    /// The keys start a longer sequence, wait for the next one
+    Pending,
+    None,
+}
+
+/// Key sequences bound to actions.
+pub struct Bindings {
+    bindings: Vec<(Vec<Key>, Action)>,
+}
+
+impl Default for Bindings {
+    fn default() -> Self {
+        let bindings = ACTIONS
+            .iter()
+            .flat_map(|(action, _, keys)| {
+                keys.iter()
+                    .filter_map(move |keys| Some((parse_keys(keys).ok()?, *action)))
+            })
+            .collect();
+        Bindings { bindings }
+    }
+}
+
+impl Bindings {
+    /// Default bindings, with the actions listed in the `[keys]` table of the config replaced.
+    /// e.g. `move_left = ["h", "<Left>"]` or `start = "gg"`.
+    pub fn new(config: &[Entry]) -> Result<Self, String> {
+        let mut result = Self::default();
+        for entry in config.iter().filter(|entry| entry.table == "keys") {
+            let action = Action::from_name(&entry.key)
+                .ok_or_else(|| format!("line {}: unknown action {}", entry.line_num, entry.key))?;
+            result.bindings.retain(|(_, bound)| *bound != action);
+            for keys in &entry.values {
+                let keys =
+                    parse_keys(keys).map_err(|err| format!("line {}: {}", entry.line_num, err))?;
+                result.bindings.push((keys, action));
+            }
+        }
+        Ok(result)
+    }
+
+    /// Finds the action bound to `keys`. A sequence that is also the start of a longer one,
+    /// waits for the longer one.
+    pub fn lookup(&self, keys: &[Key]) -> Lookup {
+        let is_prefix = self
+            .bindings
+            .iter()
+            .any(|(bound, _)| bound.len() > keys.len() && bound.starts_with(keys));
+        if is_prefix {
+            return Lookup::Pending;
+        }
+        match self.bindings.iter().find(|(bound, _)| bound == keys) {
+            Some((_, action)) => Lookup::Action(*action),
+            None => Lookup::None,
+        }
+    }
+
+    /// Keys of every action as `(keys, action name)`, in the order of the actions.
+    pub fn help(&self) -> Vec<(String, String)> {
+        ACTIONS
+            .iter()
+            .map(|(action, name, _)| {
+                let keys: Vec<String> = self
+                    .bindings
+                    .iter()
+                    .filter(|(_, bound)| bound == action)
+                    .map(|(keys, _)| keys.iter().map(key_name).collect())
+                    .collect();
+                (keys.join(" "), name.to_string())
+            })
+            .filter(|(keys, _)| !keys.is_empty())
+            .collect()
+    }
+}
+
+/// Reads keys written like in vim: plain characters, or special keys in angle brackets such as
+/// `<Left>`, `<PageDown>`, `<Esc>` and `<C-d>` for Ctrl-d.
+fn parse_keys(text: &str) -> Result<Vec<Key>, String> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(ch) = rest.chars().next() {
+        let special = rest
+            .strip_prefix('<')
+            .and_then(|tail| tail.split_once('>'))
+            .filter(|(name, _)| !name.is_empty());
+        match special {
+            Some((name, tail)) => {
+                keys.push(special_key(name).ok_or_else(|| format!("unknown key <{}>", name))?);
+                rest = tail;
+            }
+            _ => {
+                keys.push(Key::Char(ch));
+                rest = &rest[ch.len_utf8()..];
+            }
+        }
+    }
+    if keys.is_empty() {
+        return Err(String::from("empty key"));
+    }
+    Ok(keys)
+}
+
+fn special_key(name: &str) -> Option<Key> {
+    let lower = name.to_lowercase();
+    if let Some(letter) = lower.strip_prefix("c-") {
+        let mut chars = letter.chars();
+        return match (chars.next(), chars.next()) {
+            (Some('c'), None) => Some(Key::CtrlC),
+            // The terminal reads these as other keys
+            (Some('a'), None) => Some(Key::Home),
+            (Some('e'), None) => Some(Key::End),
+            (Some('h'), None) => Some(Key::Backspace),
+            (Some(ch @ 'a'..='z'), None) => Some(Key::Char((ch as u8 - b'a' + 1) as char)),
+            _ => None,
+        };
+    }
+    Some(match lower.as_str() {
+        "left" => Key::ArrowLeft,
+        "right" => Key::ArrowRight,
+        "up" => Key::ArrowUp,
+        "down" => Key::ArrowDown,
+        "pageup" => Key::PageUp,
+        "pagedown" => Key::PageDown,
+        "home" => Key::Home,
+        "end" => Key::End,
+        "enter" | "cr" => Key::Enter,
+        "esc" => Key::Escape,
+        "tab" => Key::Tab,
+        "bs" => Key::Backspace,
+        "del" => Key::Del,
+        "insert" => Key::Insert,
+        "space" => Key::Char(' '),
+        "lt" => Key::Char('<'),
+        _ => return None,
+    })
+}
+
+/// Name of a key as written in the config file.
+fn key_name(key: &Key) -> String {
+    match key {
+        Key::ArrowLeft => String::from("<Left>"),
+        Key::ArrowRight => String::from("<Right>"),
+        Key::ArrowUp => String::from("<Up>"),
+        Key::ArrowDown => String::from("<Down>"),
+        Key::PageUp => String::from("<PageUp>"),
+        Key::PageDown => String::from("<PageDown>"),
+        Key::Home => String::from("<Home>"),
+        Key::End => String::from("<End>"),
+        Key::Enter => String::from("<Enter>"),
+        Key::Escape => String::from("<Esc>"),
+        Key::Tab => String::from("<Tab>"),
+        Key::Backspace => String::from("<BS>"),
+        Key::Del => String::from("<Del>"),
+        Key::Insert => String::from("<Insert>"),
+        Key::CtrlC => String::from("<C-c>"),
+        Key::Char(' ') => String::from("<Space>"),
+        Key::Char('<') => String::from("<lt>"),
+        Key::Char(ch @ '\x01'..='\x1a') => {
+            format!("<C-{}>", ((*ch as u8) + b'a' - 1) as char)
+        }
+        Key::Char(ch) => ch.to_string(),
+        _ => String::from("<?>"),
+    }
+}
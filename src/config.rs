@@ -0,0 +1,241 @@
+use std::env;
+use std::fs;
+use std::iter::Peekable;
+use std::path::PathBuf;
+use std::str::Chars;
+
+/// A `key = value` line of the config file. Values are strings, or arrays of strings.
+pub struct Entry {
+    /// Name of the `[table]` the entry is in, empty at the top of the file
+    pub table: String,
+    pub key: String,
+    pub values: Vec<String>,
+    pub line_num: usize,
+}
+
+/// `$XDG_CONFIG_HOME/rstabs/config.toml`, or `~/.config/rstabs/config.toml`.
+pub fn path() -> Option<PathBuf> {
+    let config_dir = match env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(env::var_os("HOME")?).join(".config"),
+    };
+    Some(config_dir.join("rstabs").join("config.toml"))
+}
+
+/// Entries of the config file, none if there is no config file.
+pub fn read() -> Result<Vec<Entry>, String> {
+    let Some(path) = path() else {
+        return Ok(Vec::new());
+    };
+    match fs::read_to_string(&path) {
+        Ok(text) => parse(&text).map_err(|err| format!("{}: {}", path.display(), err)),
+        Err(_) if !path.exists() => Ok(Vec::new()),
+        Err(err) => Err(format!("{}: {}", path.display(), err)),
+    }
+}
+
+/// Reads the subset of TOML the config needs:
+/// - `# comments`, on their own line or after a value
+/// - `[table]` headers, the name is kept as written so `[a.b]` is the table `a.b`
+/// - `key = value` where the key is a bare or quoted word, and the value a basic string with
+///   the `\n`, `\t`, `\"` and `\\` escapes, a literal `'string'`, a bare word or an array of
+///   them on a single line
+///
+/// Numbers and booleans are read as the bare words they are written as. Multi-line strings and
+/// arrays, inline tables, arrays of tables and dotted keys are errors or read as bare words.
+pub fn parse(text: &str) -> Result<Vec<Entry>, String> {
+    let mut entries = Vec::new();
+    let mut table = String::new();
+    for (line_num, line) in text.lines().enumerate() {
+        let line_num = line_num + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(name) = line.strip_prefix('[') {
+            let name = name.split('#').next().unwrap_or("").trim_end();
+            match name.strip_suffix(']') {
+                Some(name) => table = name.trim().to_string(),
+                None => return Err(format!("line {}: missing ']'", line_num)),
+            }
+            continue;
+        }
+        let mut chars = line.chars().peekable();
+        let key = parse_item(&mut chars).map_err(|err| format!("line {}: {}", line_num, err))?;
+        skip_spaces(&mut chars);
+        if chars.next() != Some('=') {
+            return Err(format!("line {}: expected '=' after {}", line_num, key));
+        }
+        let values =
+            parse_value(&mut chars).map_err(|err| format!("line {}: {}", line_num, err))?;
+        entries.push(Entry {
+            table: table.clone(),
+            key,
+            values,
+            line_num,
+        });
+    }
+    Ok(entries)
+}
+
+fn skip_spaces(chars: &mut Peekable<Chars>) {
+    while chars.next_if(|ch| ch.is_whitespace()).is_some() {}
+}
+
+fn parse_value(chars: &mut Peekable<Chars>) -> Result<Vec<String>, String> {
+    skip_spaces(chars);
+    let mut values = Vec::new();
+    if chars.next_if_eq(&'[').is_some() {
+        loop {
+            skip_spaces(chars);
+            if chars.next_if_eq(&']').is_some() {
+                break;
+            }
+            values.push(parse_item(chars)?);
+            skip_spaces(chars);
+            match chars.next() {
+                Some(',') => (),
+                Some(']') => break,
+                _ => return Err(String::from("expected ',' or ']' in array")),
+            }
+        }
+    } else {
+        values.push(parse_item(chars)?);
+    }
+    skip_spaces(chars);
+    match chars.next() {
+        None | Some('#') => Ok(values),
+        Some(ch) => Err(format!("unexpected '{}'", ch)),
+    }
+}
+
+/// A quoted string or a bare word.
+fn parse_item(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    skip_spaces(chars);
+    let mut item = String::new();
+    match chars.peek() {
+        Some('"') => {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('"') => break,
+                    Some('\\') => match chars.next() {
+                        Some('n') => item.push('\n'),
+                        Some('t') => item.push('\t'),
+                        Some(ch @ ('"' | '\\')) => item.push(ch),
+                        Some(ch) => return Err(format!("unknown escape '\\{}'", ch)),
+                        None => return Err(String::from("unterminated string")),
+                    },
+                    Some(ch) => item.push(ch),
+                    None => return Err(String::from("unterminated string")),
+                }
+            }
+        }
+        Some('\'') => {
+            chars.next();
+            loop {
+                match chars.next() {
+                    Some('\'') => break,
+                    Some(ch) => item.push(ch),
+                    None => return Err(String::from("unterminated string")),
+                }
+            }
+        }
+        _ => {
+            while let Some(ch) =
+                chars.next_if(|ch| !ch.is_whitespace() && !matches!(ch, '=' | ',' | ']' | '#'))
+            {
+                item.push(ch);
+            }
+            if item.is_empty() {
+                return Err(String::from("expected a value"));
+            }
+        }
+    }
+    Ok(item)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse_ok(text: &str) -> Vec<(String, String, Vec<String>, usize)> {
+        parse(text)
+            .unwrap()
+            .into_iter()
+            .map(|entry| (entry.table, entry.key, entry.values, entry.line_num))
+            .collect()
+    }
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn tables_and_keys() {
+        let text =
+            "# rstabs\ntheme = dark\n\n[keys]\n  quit = \"q\" # leave\n[ colors . x ]\nfg = 1\n";
+        assert_eq!(
+            parse_ok(text),
+            vec![
+                (String::new(), "theme".into(), strings(&["dark"]), 2),
+                ("keys".into(), "quit".into(), strings(&["q"]), 5),
+                ("colors . x".into(), "fg".into(), strings(&["1"]), 7),
+            ]
+        );
+    }
+
+    #[test]
+    fn strings_and_bare_words() {
+        let text = r#"a = "tab\tq \"x\" \\ # not a comment"
+b = 'C:\dir "x"'
+"quoted key" = true
+c=1.5"#;
+        let values: Vec<_> = parse_ok(text)
+            .into_iter()
+            .map(|(_, key, values, _)| (key, values))
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                ("a".into(), strings(&["tab\tq \"x\" \\ # not a comment"])),
+                ("b".into(), strings(&["C:\\dir \"x\""])),
+                ("quoted key".into(), strings(&["true"])),
+                ("c".into(), strings(&["1.5"])),
+            ]
+        );
+    }
+
+    #[test]
+    fn arrays() {
+        let text = "a = []\nb = [ x ]\nc = [\"j\", 'k', down,] # keys\nd = [\"]\", ',']";
+        let values: Vec<_> = parse_ok(text)
+            .into_iter()
+            .map(|(_, _, values, _)| values)
+            .collect();
+        assert_eq!(
+            values,
+            vec![
+                strings(&[]),
+                strings(&["x"]),
+                strings(&["j", "k", "down"]),
+                strings(&["]", ","]),
+            ]
+        );
+    }
+
+    #[test]
+    fn errors_name_the_line() {
+        let error = |text: &str| parse(text).err().unwrap();
+        assert_eq!(error("\n[keys"), "line 2: missing ']'");
+        assert_eq!(error("a b"), "line 1: expected '=' after a");
+        assert_eq!(error("a ="), "line 1: expected a value");
+        assert_eq!(error("a = \"x"), "line 1: unterminated string");
+        assert_eq!(error("a = 'x"), "line 1: unterminated string");
+        assert_eq!(error("a = \"\\q\""), "line 1: unknown escape '\\q'");
+        assert_eq!(error("a = x y"), "line 1: unexpected 'y'");
+        assert_eq!(error("a = [x y]"), "line 1: expected ',' or ']' in array");
+        // Arrays end on the line they start
+        assert_eq!(error("a = [\n  x,\n]"), "line 1: expected a value");
+    }
+}
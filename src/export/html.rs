@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::time::Duration;
+
+use super::svg::{self, escape};
+use super::{beat_places, pages, Page};
+use crate::layout::playback::Sequencer;
+use crate::layout::{roadmap, TabLayout};
+
+/// Plays the beats: `BEATS` holds `[start in ms, page, x, y, width, height]` of every beat in
+/// play order and `TOTAL` the length of the song in ms.
+const SCRIPT: &str = r#"
+const pages = document.querySelectorAll(".page");
+const cursor = document.getElementById("cursor");
+const play = document.getElementById("play");
+const clock = document.getElementById("time");
+let start = null;
+let index = 0;
+let shownTop = null;
+
+function timeText(ms) {
+  const seconds = Math.floor(ms / 1000);
+  return Math.floor(seconds / 60) + ":" + String(seconds % 60).padStart(2, "0");
+}
+
+function show(i) {
+  const [time, page, x, y, width, height] = BEATS[i];
+  if (cursor.parentNode !== pages[page]) pages[page].appendChild(cursor);
+  cursor.style.left = x + "pt";
+  cursor.style.top = y + "pt";
+  cursor.style.width = width + "pt";
+  cursor.style.height = height + "pt";
+  cursor.style.display = "block";
+  clock.textContent = timeText(time) + " / " + timeText(TOTAL);
+  if (shownTop !== page + ":" + y) {
+    shownTop = page + ":" + y;
+    cursor.scrollIntoView({ block: "center", behavior: "smooth" });
+  }
+}
+
+function stop() {
+  clearInterval(start.timer);
+  start = null;
+  play.textContent = "Play";
+}
+
+function tick() {
+  const time = performance.now() - start.at;
+  if (time >= TOTAL) {
+    stop();
+    index = 0;
+    return;
+  }
+  while (index + 1 < BEATS.length && BEATS[index + 1][0] <= time) index++;
+  show(index);
+}
+
+play.addEventListener("click", () => {
+  if (start) {
+    stop();
+    return;
+  }
+  if (BEATS.length === 0) return;
+  start = { at: performance.now() - BEATS[index][0], timer: setInterval(tick, 15) };
+  play.textContent = "Pause";
+  tick();
+});
+
+// A click on a beat moves the cursor there
+pages.forEach((page, pageNum) => page.addEventListener("click", (event) => {
+  const box = page.getBoundingClientRect();
+  const scale = box.width / page.dataset.width;
+  const x = (event.clientX - box.left) / scale;
+  const y = (event.clientY - box.top) / scale;
+  const found = BEATS.findIndex(([, p, bx, by, w, h]) =>
+    p === pageNum && x >= bx && x < bx + w && y >= by && y < by + h);
+  if (found < 0) return;
+  index = found;
+  if (start) start.at = performance.now() - BEATS[index][0];
+  show(index);
+}));
+"#;
+
+/// A page that needs nothing else: every page of the track as SVG, the time every beat starts
+/// at in play order, and a script moving a cursor over the beats as they are played.
+pub fn document(title: &str, layout: &TabLayout, tempo: i16, page: &Page) -> String {
+    let measure_count = layout.track.measures.len();
+    let places = beat_places(layout, 0..measure_count, page);
+    let place_of: HashMap<(usize, usize), usize> = places
+        .iter()
+        .enumerate()
+        .map(|(index, place)| ((place.measure, place.beat), index))
+        .collect();
+    let beats: Vec<_> = (0..measure_count)
+        .map(|measure_num| layout.measure_beats(measure_num).beats)
+        .collect();
+    let sequencer = Sequencer::new(
+        &beats,
+        layout.headers,
+        &roadmap::roadmap(layout.headers),
+        tempo,
+    );
+
+    let mut timing = String::new();
+    let mut time = Duration::ZERO;
+    for step in sequencer.steps() {
+        if let Some(place) = place_of
+            .get(&(step.measure, step.beat))
+            .map(|index| &places[*index])
+        {
+            writeln!(
+                timing,
+                "  [{}, {}, {}, {}, {}, {}],",
+                time.as_millis(),
+                place.page,
+                place.at.0,
+                place.at.1,
+                place.size.0,
+                place.size.1
+            )
+            .unwrap();
+        }
+        time += step.duration;
+    }
+
+    let mut html = String::from("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+    writeln!(html, "<title>{}</title>", escape(title)).unwrap();
+    html.push_str(
+        "<style>\n\
+         body{margin:0;background:#ddd;font-family:sans-serif}\n\
+         .controls{position:sticky;top:0;z-index:1;padding:8px;background:#333;color:#fff}\n\
+         .controls button{min-width:5em;margin-right:1em}\n\
+         .page{position:relative;margin:16px auto;box-shadow:0 1px 4px #888}\n\
+         .page svg{display:block}\n\
+         #cursor{display:none;position:absolute;background:#07c3;border-radius:2px;pointer-events:none}\n\
+         </style>\n</head>\n<body>\n",
+    );
+    writeln!(
+        html,
+        "<div class=\"controls\"><button id=\"play\">Play</button><b>{}</b> <span id=\"time\"></span></div>",
+        escape(title)
+    )
+    .unwrap();
+    for shapes in pages(layout, 0..measure_count, page) {
+        writeln!(
+            html,
+            "<div class=\"page\" data-width=\"{w}\" style=\"width:{w}pt;height:{h}pt\">",
+            w = page.width,
+            h = page.height
+        )
+        .unwrap();
+        html.push_str(&svg::document(page, &shapes));
+        html.push_str("</div>\n");
+    }
+    html.push_str("<div id=\"cursor\"></div>\n<script>\n");
+    writeln!(html, "const BEATS = [\n{}];", timing).unwrap();
+    writeln!(html, "const TOTAL = {};", time.as_millis()).unwrap();
+    html.push_str(SCRIPT);
+    html.push_str("</script>\n</body>\n</html>\n");
+    html
+}
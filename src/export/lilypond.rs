@@ -0,0 +1,256 @@
+use std::fmt::Write;
+
+use guitarpro::beat::Beat;
+use guitarpro::enums::{NoteType, SlideType};
+use guitarpro::gp::Song;
+use guitarpro::headers::MeasureHeader;
+use guitarpro::track::Track;
+
+use crate::layout::notation::Clef;
+
+const SHARP_NAMES: [&str; 12] = [
+    "c", "cis", "d", "dis", "e", "f", "fis", "g", "gis", "a", "ais", "b",
+];
+const FLAT_NAMES: [&str; 12] = [
+    "c", "des", "d", "ees", "e", "f", "ges", "g", "aes", "a", "bes", "b",
+];
+// Tonic of every key signature, from 7 flats to 7 sharps
+const MAJOR_KEYS: [&str; 15] = [
+    "ces", "ges", "des", "aes", "ees", "bes", "f", "c", "g", "d", "a", "e", "b", "fis", "cis",
+];
+const MINOR_KEYS: [&str; 15] = [
+    "aes", "ees", "bes", "f", "c", "g", "d", "a", "e", "b", "fis", "cis", "gis", "dis", "ais",
+];
+
+/// The tracks as a LilyPond score, every track on a staff with a tab staff under it. The staff
+/// sounds an octave under its clef as for a guitar, in bass clef when no string is as high as
+/// middle C. Percussion tracks are left out. Only the first voice of every measure is written.
+pub fn document(song: &Song, track_nums: &[usize]) -> String {
+    let mut ly = String::from("\\version \"2.24.0\"\n\n");
+    writeln!(ly, "\\header {{").unwrap();
+    writeln!(ly, "  title = {}", string(&song.name)).unwrap();
+    if !song.artist.is_empty() {
+        writeln!(ly, "  composer = {}", string(&song.artist)).unwrap();
+    }
+    writeln!(ly, "  tagline = ##f\n}}\n").unwrap();
+
+    let tracks: Vec<(String, &Track)> = track_nums
+        .iter()
+        .map(|track_num| &song.tracks[*track_num])
+        .filter(|track| !track.percussion_track)
+        .enumerate()
+        .map(|(num, track)| (variable_name(num), track))
+        .collect();
+    for (name, track) in &tracks {
+        writeln!(ly, "{} = {{", name).unwrap();
+        writeln!(ly, "  \\tempo 4 = {}", song.tempo).unwrap();
+        write_music(&mut ly, track, &song.measure_headers);
+        writeln!(ly, "}}\n").unwrap();
+    }
+
+    writeln!(ly, "\\score {{\n  <<").unwrap();
+    for (name, track) in &tracks {
+        // Tuning from the lowest string
+        let tuning: Vec<String> = track
+            .strings
+            .iter()
+            .rev()
+            .map(|(_, pitch)| pitch_name(*pitch as i16, false))
+            .collect();
+        writeln!(
+            ly,
+            "    \\new StaffGroup \\with {{ instrumentName = {} }} <<",
+            string(&track.name)
+        )
+        .unwrap();
+        let clef = match Clef::for_tuning(&track.strings) {
+            Clef::Treble => "treble_8",
+            Clef::Bass => "bass_8",
+        };
+        writeln!(ly, "      \\new Staff {{ \\clef \"{}\" \\{} }}", clef, name).unwrap();
+        writeln!(
+            ly,
+            "      \\new TabStaff \\with {{ stringTunings = \\stringTuning <{}> }} {{ \\{} }}",
+            tuning.join(" "),
+            name
+        )
+        .unwrap();
+        writeln!(ly, "    >>").unwrap();
+    }
+    writeln!(ly, "  >>\n  \\layout {{ }}\n}}").unwrap();
+    ly
+}
+
+/// `trackA`, `trackB`... LilyPond variables cannot have digits.
+fn variable_name(num: usize) -> String {
+    let mut letters = String::new();
+    let mut num = num;
+    loop {
+        letters.insert(0, (b'A' + (num % 26) as u8) as char);
+        if num < 26 {
+            break;
+        }
+        num = num / 26 - 1;
+    }
+    format!("track{}", letters)
+}
+
+fn string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Pitch with the octave marks, `c'` being middle C.
+fn pitch_name(pitch: i16, use_flats: bool) -> String {
+    let names = if use_flats { FLAT_NAMES } else { SHARP_NAMES };
+    let mut name = names[pitch.rem_euclid(12) as usize].to_string();
+    let octave = pitch.div_euclid(12) - 4;
+    let mark = if octave > 0 { "'" } else { "," };
+    name.push_str(&mark.repeat(octave.unsigned_abs() as usize));
+    name
+}
+
+fn duration(beat: &Beat) -> String {
+    let duration = &beat.duration;
+    let dots = if duration.double_dotted {
+        ".."
+    } else if duration.dotted {
+        "."
+    } else {
+        ""
+    };
+    format!("{}{}", duration.value, dots)
+}
+
+/// Measures of the first voice, with the time and key signatures where they change and the
+/// rehearsal markers.
+fn write_music(ly: &mut String, track: &Track, headers: &[MeasureHeader]) {
+    let beats: Vec<&Beat> = track
+        .measures
+        .iter()
+        .flat_map(|measure| measure.voices.first().map_or(&[][..], |voice| &voice.beats))
+        .collect();
+    let mut beat_num = 0;
+    for (measure_num, measure) in track.measures.iter().enumerate() {
+        ly.push_str("  ");
+        let header = headers.get(measure_num);
+        let previous = measure_num
+            .checked_sub(1)
+            .and_then(|previous| headers.get(previous));
+        if let Some(header) = header {
+            let time = &header.time_signature;
+            let time_changed = previous.is_none_or(|previous| {
+                previous.time_signature.numerator != time.numerator
+                    || previous.time_signature.denominator.value != time.denominator.value
+            });
+            if time_changed {
+                write!(ly, "\\time {}/{} ", time.numerator, time.denominator.value).unwrap();
+            }
+            if let Some(marker) = &header.marker {
+                write!(ly, "\\mark {} ", string(&marker.title)).unwrap();
+            }
+        }
+        let key = &measure.key_signature;
+        let key_changed = measure_num.checked_sub(1).is_none_or(|previous| {
+            let previous = &track.measures[previous].key_signature;
+            previous.key != key.key || previous.is_minor != key.is_minor
+        });
+        if key_changed {
+            let index = (key.key.clamp(-7, 7) + 7) as usize;
+            let (tonic, mode) = if key.is_minor {
+                (MINOR_KEYS[index], "minor")
+            } else {
+                (MAJOR_KEYS[index], "major")
+            };
+            write!(ly, "\\key {} \\{} ", tonic, mode).unwrap();
+        }
+
+        let use_flats = key.key < 0;
+        let measure_beats = measure.voices.first().map_or(0, |voice| voice.beats.len());
+        // Notes of a tuplet are grouped, `(enters, times)` of the open group
+        let mut tuplet: Option<(u8, u8)> = None;
+        for beat in &beats[beat_num..beat_num + measure_beats] {
+            let duration = &beat.duration;
+            let beat_tuplet = (duration.tuplet_enters != duration.tuplet_times)
+                .then_some((duration.tuplet_enters, duration.tuplet_times));
+            if tuplet != beat_tuplet {
+                if tuplet.is_some() {
+                    ly.push_str("} ");
+                }
+                if let Some((enters, times)) = beat_tuplet {
+                    write!(ly, "\\tuplet {}/{} {{ ", enters, times).unwrap();
+                }
+                tuplet = beat_tuplet;
+            }
+            let next = beats.get(beat_num + 1);
+            write_beat(ly, beat, next.copied(), &track.strings, use_flats);
+            ly.push(' ');
+            beat_num += 1;
+        }
+        if tuplet.is_some() {
+            ly.push_str("} ");
+        }
+        ly.push_str("|\n");
+    }
+}
+
+/// A rest, a note or a chord with its string numbers. A tie goes to the notes tied in the
+/// `next` beat, slides to another note become glissandi.
+fn write_beat(
+    ly: &mut String,
+    beat: &Beat,
+    next: Option<&Beat>,
+    tuning: &[(i8, i8)],
+    use_flats: bool,
+) {
+    let notes: Vec<String> = beat
+        .notes
+        .iter()
+        .filter(|note| note.string >= 1 && note.string as usize <= tuning.len())
+        .filter_map(|note| {
+            let mut text = match note.kind {
+                NoteType::Normal | NoteType::Tie => String::new(),
+                NoteType::Dead => String::from("\\deadNote "),
+                NoteType::Rest | NoteType::Unknown(_) => return None,
+            };
+            if note.effect.ghost_note {
+                text.push_str("\\parenthesize ");
+            }
+            let pitch = tuning[note.string as usize - 1].1 as i16 + note.value;
+            write!(text, "{}\\{}", pitch_name(pitch, use_flats), note.string).unwrap();
+            Some(text)
+        })
+        .collect();
+    if notes.is_empty() {
+        write!(ly, "r{}", duration(beat)).unwrap();
+        return;
+    }
+    write!(ly, "<{}>{}", notes.join(" "), duration(beat)).unwrap();
+    let is_tied = next.is_some_and(|next| {
+        next.notes.iter().any(|next_note| {
+            next_note.kind == NoteType::Tie
+                && beat
+                    .notes
+                    .iter()
+                    .any(|note| note.string == next_note.string)
+        })
+    });
+    if is_tied {
+        ly.push('~');
+    }
+    let slides = beat.notes.iter().flat_map(|note| &note.effect.slides);
+    let mut glissando = false;
+    let mut bend_after: Option<i8> = None;
+    for slide in slides {
+        match slide {
+            SlideType::ShiftSlideTo | SlideType::LegatoSlideTo => glissando = true,
+            SlideType::OutUpWards => bend_after = Some(4),
+            SlideType::OutDownwards => bend_after = Some(-4),
+            SlideType::IntoFromAbove | SlideType::IntoFromBelow | SlideType::None => (),
+        }
+    }
+    if glissando {
+        ly.push_str("\\glissando");
+    } else if let Some(amount) = bend_after {
+        write!(ly, "\\bendAfter #{}", amount).unwrap();
+    }
+}
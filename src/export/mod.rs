@@ -0,0 +1,476 @@
+pub mod html;
+pub mod lilypond;
+pub mod pdf;
+pub mod svg;
+
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+use crate::layout::{Cell, MeasureLayout, Rhythm, Role, TabLayout};
+
+/// Width of a cell of the layout grid, the width of a character of a 10 point monospace font
+pub const CELL_WIDTH: f32 = 6.0;
+pub const ROW_HEIGHT: f32 = 11.0;
+pub const FONT_SIZE: f32 = 10.0;
+/// Space under the strings for the stems of the rhythm
+const RHYTHM_HEIGHT: f32 = 26.0;
+/// Space between two systems
+const SYSTEM_GAP: f32 = 16.0;
+
+/// Size of a printed page and its margins, in points.
+#[derive(Clone, Copy)]
+pub struct Page {
+    pub width: f32,
+    pub height: f32,
+    pub margin: f32,
+    /// Space left above the tab on every page for a header
+    pub header: f32,
+    /// Space left above the tab on the first page for a title, on top of the header
+    pub title: f32,
+    /// Most systems on a page, as many as fit when `None`
+    pub systems: Option<usize>,
+}
+
+impl Default for Page {
+    /// A4 with half an inch of margin
+    fn default() -> Self {
+        Page {
+            width: 595.0,
+            height: 842.0,
+            margin: 36.0,
+            header: 0.0,
+            title: 0.0,
+            systems: None,
+        }
+    }
+}
+
+/// What the vector backends draw, in points from the top left corner of the page.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Shape {
+    Line {
+        from: (f32, f32),
+        to: (f32, f32),
+        width: f32,
+    },
+    /// Curve from `from` to `to` bending up by `height`, for ties and hammer-ons
+    Arc {
+        from: (f32, f32),
+        to: (f32, f32),
+        height: f32,
+    },
+    Dot {
+        center: (f32, f32),
+        radius: f32,
+    },
+    /// Text starting at `at` on its baseline. With `background` the string line behind it is
+    /// covered.
+    Text {
+        at: (f32, f32),
+        text: String,
+        role: Role,
+        background: bool,
+    },
+}
+
+/// Where a beat is drawn: the box around its column of notes, from the first string down to
+/// the rhythm, on the page `page`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BeatPlace {
+    pub measure: usize,
+    pub beat: usize,
+    pub page: usize,
+    pub at: (f32, f32),
+    pub size: (f32, f32),
+}
+
+/// Measures of `range` on pages, as many side by side as fit the width of the page. Every page
+/// is a list of shapes.
+pub fn pages(layout: &TabLayout, range: Range<usize>, page: &Page) -> Vec<Vec<Shape>> {
+    let measures = layout.measures(range);
+    paginate(&measures, page)
+        .into_iter()
+        .map(|systems| {
+            let mut shapes = Vec::new();
+            for (top, system) in systems {
+                draw_system(&mut shapes, &measures[system], page.margin, top);
+            }
+            shapes
+        })
+        .collect()
+}
+
+/// The place of every beat of `range` on the pages drawn by `pages`.
+pub fn beat_places(layout: &TabLayout, range: Range<usize>, page: &Page) -> Vec<BeatPlace> {
+    let first = range.start;
+    let measures = layout.measures(range);
+    let mut places = Vec::new();
+    for (page_num, systems) in paginate(&measures, page).into_iter().enumerate() {
+        for (top, system) in systems {
+            let system_measures = &measures[system.clone()];
+            let y = top + rows_above_strings(system_measures) as f32 * ROW_HEIGHT;
+            let mut x = page.margin;
+            for (measure_num, measure) in system.zip(system_measures) {
+                let height = measure.strings.len() as f32 * ROW_HEIGHT + RHYTHM_HEIGHT;
+                for beat in 0..measure.rhythm.len() {
+                    places.push(BeatPlace {
+                        measure: first + measure_num,
+                        beat,
+                        page: page_num,
+                        at: (x + MeasureLayout::beat_column(beat) as f32 * CELL_WIDTH, y),
+                        size: (MeasureLayout::BEAT_WIDTH as f32 * CELL_WIDTH, height),
+                    });
+                }
+                x += measure_width(measure);
+            }
+        }
+    }
+    places
+}
+
+/// Systems of every page, as the top of the system and its measures in `measures`.
+fn paginate(measures: &[MeasureLayout], page: &Page) -> Vec<Vec<(f32, Range<usize>)>> {
+    let mut pages = Vec::new();
+    let mut systems = Vec::new();
+    let mut y = page.margin + page.header + page.title;
+    let mut start = 0;
+    for system in self::systems(measures, page.width - 2.0 * page.margin) {
+        let height = system_height(system);
+        let is_full = page.systems.is_some_and(|most| systems.len() >= most)
+            || y + height > page.height - page.margin;
+        if is_full && !systems.is_empty() {
+            pages.push(systems);
+            systems = Vec::new();
+            y = page.margin + page.header;
+        }
+        systems.push((y, start..start + system.len()));
+        start += system.len();
+        y += height + SYSTEM_GAP;
+    }
+    pages.push(systems);
+    pages
+}
+
+/// Path of the page `page_num` of `pages`: `path` itself when there is a single page, else
+/// `tab-1.svg`, `tab-2.svg` and so on.
+pub fn page_path(path: &Path, page_num: usize, pages: usize) -> PathBuf {
+    if pages == 1 {
+        return path.to_path_buf();
+    }
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let name = match path.extension() {
+        Some(extension) => format!("{}-{}.{}", stem, page_num + 1, extension.to_string_lossy()),
+        None => format!("{}-{}", stem, page_num + 1),
+    };
+    path.with_file_name(name)
+}
+
+fn measure_width(measure: &MeasureLayout) -> f32 {
+    measure.grid.width() as f32 * CELL_WIDTH
+}
+
+/// Measures split into lines that fit in `width`, a measure wider than that is alone on its
+/// line.
+fn systems(measures: &[MeasureLayout], width: f32) -> Vec<&[MeasureLayout]> {
+    let mut systems = Vec::new();
+    let mut start = 0;
+    let mut line_width = 0.0;
+    for (measure_num, measure) in measures.iter().enumerate() {
+        if line_width + measure_width(measure) > width && measure_num > start {
+            systems.push(&measures[start..measure_num]);
+            start = measure_num;
+            line_width = 0.0;
+        }
+        line_width += measure_width(measure);
+    }
+    if start < measures.len() {
+        systems.push(&measures[start..]);
+    }
+    systems
+}
+
+/// Rows above the strings of the measure with the most of them, the strings of every measure
+/// of a system are drawn at the same height.
+fn rows_above_strings(system: &[MeasureLayout]) -> usize {
+    system
+        .iter()
+        .map(|measure| measure.strings.start)
+        .max()
+        .unwrap_or(0)
+}
+
+fn system_height(system: &[MeasureLayout]) -> f32 {
+    let above = rows_above_strings(system);
+    let rows = system
+        .iter()
+        .map(|measure| above - measure.strings.start + measure.grid.rows.len())
+        .max()
+        .unwrap_or(0);
+    rows as f32 * ROW_HEIGHT + RHYTHM_HEIGHT
+}
+
+fn draw_system(shapes: &mut Vec<Shape>, system: &[MeasureLayout], left: f32, top: f32) {
+    let above = rows_above_strings(system);
+    let mut x = left;
+    for measure in system {
+        let y = top + (above - measure.strings.start) as f32 * ROW_HEIGHT;
+        draw_measure(shapes, measure, x, y);
+        x += measure_width(measure);
+    }
+    // Closing bar line of the system
+    if let Some(last) = system.last() {
+        let y = top + above as f32 * ROW_HEIGHT;
+        let strings = last.strings.len() as f32;
+        shapes.push(Shape::Line {
+            from: (x, y + ROW_HEIGHT / 2.0),
+            to: (x, y + (strings - 0.5) * ROW_HEIGHT),
+            width: 1.0,
+        });
+    }
+}
+
+/// Top of the row `row_num` of a measure, the rows under the strings leave room for the rhythm.
+fn row_top(measure: &MeasureLayout, top: f32, row_num: usize) -> f32 {
+    let rhythm = if row_num >= measure.strings.end {
+        RHYTHM_HEIGHT
+    } else {
+        0.0
+    };
+    top + row_num as f32 * ROW_HEIGHT + rhythm
+}
+
+fn draw_measure(shapes: &mut Vec<Shape>, measure: &MeasureLayout, left: f32, top: f32) {
+    let width = measure_width(measure);
+    for (row_num, row) in measure.grid.rows.iter().enumerate() {
+        let y = row_top(measure, top, row_num);
+        let is_string = measure.strings.contains(&row_num);
+        if is_string {
+            shapes.push(Shape::Line {
+                from: (left + CELL_WIDTH / 2.0, y + ROW_HEIGHT / 2.0),
+                to: (left + width, y + ROW_HEIGHT / 2.0),
+                width: 0.5,
+            });
+            draw_arcs(shapes, row, left, width, y);
+        }
+        draw_row(shapes, row, left, y, is_string);
+    }
+    draw_rhythm(
+        shapes,
+        &measure.rhythm,
+        left,
+        top + measure.strings.end as f32 * ROW_HEIGHT,
+    );
+}
+
+/// Lines and glyphs of a row. String lines are drawn by the caller, the text on them covers
+/// the line.
+fn draw_row(shapes: &mut Vec<Shape>, row: &[Cell], left: f32, top: f32, is_string: bool) {
+    let middle = top + ROW_HEIGHT / 2.0;
+    let bottom = top + ROW_HEIGHT;
+    let mut text: Option<(usize, String, Role)> = None;
+    let mut line_start: Option<usize> = None;
+    for (column, cell) in row.iter().enumerate() {
+        let x = left + column as f32 * CELL_WIDTH;
+        let center = x + CELL_WIDTH / 2.0;
+        let base = cell.glyph.chars().next().unwrap_or(' ');
+        // Lines and dots are drawn as shapes, the rest as text
+        let is_glyph = !" ─│├╭╰╟╓╙╢╖╜┌•╱╲".contains(base);
+        // A run of text ends at a line or a cell of another role
+        if let Some((start, run, role)) = text.take() {
+            if is_glyph && role == cell.role {
+                text = Some((start, run, role));
+            } else {
+                push_text(shapes, left, start, run, role, top, is_string);
+            }
+        }
+        if is_glyph {
+            match &mut text {
+                Some((_, run, _)) => run.push(base),
+                None => text = Some((column, base.to_string(), cell.role)),
+            }
+        }
+        // Lines of the staff and the brackets, the strings are drawn whole
+        match (base == '─' && !is_string, line_start) {
+            (true, None) => line_start = Some(column),
+            (false, Some(start)) => {
+                push_line(shapes, left, start, column, middle);
+                line_start = None;
+            }
+            _ => (),
+        }
+        let vertical = |from: f32, to: f32, width: f32| Shape::Line {
+            from: (center, from),
+            to: (center, to),
+            width,
+        };
+        match base {
+            '│' | '├' => shapes.push(vertical(top, bottom, 1.0)),
+            '╭' => shapes.push(vertical(middle, bottom, 1.0)),
+            '╰' => shapes.push(vertical(top, middle, 1.0)),
+            '╟' | '╢' => shapes.push(vertical(top, bottom, 2.0)),
+            '╓' | '╖' => shapes.push(vertical(middle, bottom, 2.0)),
+            '╙' | '╜' => shapes.push(vertical(top, middle, 2.0)),
+            '┌' => {
+                shapes.push(vertical(middle, bottom, 0.5));
+                shapes.push(Shape::Line {
+                    from: (center, middle),
+                    to: (x + CELL_WIDTH, middle),
+                    width: 0.5,
+                });
+            }
+            '•' => shapes.push(Shape::Dot {
+                center: (center, middle),
+                radius: 1.5,
+            }),
+            '╱' => shapes.push(Shape::Line {
+                from: (x, bottom - 2.0),
+                to: (x + CELL_WIDTH, top + 2.0),
+                width: 0.8,
+            }),
+            '╲' => shapes.push(Shape::Line {
+                from: (x, top + 2.0),
+                to: (x + CELL_WIDTH, bottom - 2.0),
+                width: 0.8,
+            }),
+            _ => (),
+        }
+    }
+    if let Some((start, run, role)) = text {
+        push_text(shapes, left, start, run, role, top, is_string);
+    }
+    if let Some(start) = line_start {
+        push_line(shapes, left, start, row.len(), middle);
+    }
+}
+
+fn push_text(
+    shapes: &mut Vec<Shape>,
+    left: f32,
+    column: usize,
+    text: String,
+    role: Role,
+    top: f32,
+    background: bool,
+) {
+    shapes.push(Shape::Text {
+        at: (
+            left + column as f32 * CELL_WIDTH,
+            top + ROW_HEIGHT - (ROW_HEIGHT - FONT_SIZE * 0.7) / 2.0,
+        ),
+        text,
+        role,
+        background,
+    });
+}
+
+fn push_line(shapes: &mut Vec<Shape>, left: f32, start: usize, end: usize, y: f32) {
+    shapes.push(Shape::Line {
+        from: (left + start as f32 * CELL_WIDTH, y),
+        to: (left + end as f32 * CELL_WIDTH, y),
+        width: 0.5,
+    });
+}
+
+/// Ties and hammer-ons of a string, from the combining marks the layout puts over the notes.
+/// An arc going on from or to another measure starts or ends at its edge.
+fn draw_arcs(shapes: &mut Vec<Shape>, row: &[Cell], left: f32, width: f32, top: f32) {
+    // Marks of the start and the end of a tie, then of a hammer-on
+    const ARCS: [(char, char); 2] = [('\u{fe27}', '\u{fe28}'), ('\u{fe20}', '\u{fe21}')];
+    let y = top + 1.0;
+    for (start_mark, end_mark) in ARCS {
+        let mut start: Option<f32> = None;
+        for (column, cell) in row.iter().enumerate() {
+            let center = left + (column as f32 + 0.5) * CELL_WIDTH;
+            if cell.glyph.contains(end_mark) {
+                shapes.push(Shape::Arc {
+                    from: (start.take().unwrap_or(left), y),
+                    to: (center, y),
+                    height: 4.0,
+                });
+            }
+            if cell.glyph.contains(start_mark) {
+                start = Some(center);
+            }
+        }
+        if let Some(start) = start {
+            shapes.push(Shape::Arc {
+                from: (start, y),
+                to: (left + width, y),
+                height: 4.0,
+            });
+        }
+    }
+}
+
+/// Number of flags or beams of a note value
+fn flags(rhythm: &Rhythm) -> u32 {
+    if rhythm.rest || rhythm.value < 8 {
+        0
+    } else {
+        rhythm.value.ilog2() - 2
+    }
+}
+
+/// Stems under the fret numbers, beams between the beats shorter than a quarter, dots and
+/// tuplet numbers.
+fn draw_rhythm(shapes: &mut Vec<Shape>, rhythm: &[Rhythm], left: f32, top: f32) {
+    let stem_top = top + 3.0;
+    let stem_bottom = top + RHYTHM_HEIGHT - 9.0;
+    let stem_x =
+        |beat_num: usize| left + (MeasureLayout::fret_column(beat_num) as f32 + 0.5) * CELL_WIDTH;
+    for (beat_num, beat) in rhythm.iter().enumerate() {
+        if beat.rest || beat.value < 2 {
+            continue;
+        }
+        let x = stem_x(beat_num);
+        let from = if beat.value == 2 {
+            (stem_top + stem_bottom) / 2.0
+        } else {
+            stem_top
+        };
+        shapes.push(Shape::Line {
+            from: (x, from),
+            to: (x, stem_bottom),
+            width: 0.8,
+        });
+        for level in 0..flags(beat) {
+            let y = stem_bottom - level as f32 * 3.0;
+            let next = rhythm.get(beat_num + 1).map_or(0, flags);
+            let previous = beat_num
+                .checked_sub(1)
+                .and_then(|previous| rhythm.get(previous))
+                .map_or(0, flags);
+            if next > level {
+                shapes.push(Shape::Line {
+                    from: (x, y),
+                    to: (stem_x(beat_num + 1), y),
+                    width: 2.0,
+                });
+            } else if previous <= level {
+                shapes.push(Shape::Line {
+                    from: (x, y),
+                    to: (x + CELL_WIDTH * 1.5, y),
+                    width: 2.0,
+                });
+            }
+        }
+        if beat.dotted {
+            shapes.push(Shape::Dot {
+                center: (x + 3.0, stem_bottom + 2.0),
+                radius: 1.0,
+            });
+        }
+        let previous_tuplet = beat_num
+            .checked_sub(1)
+            .and_then(|previous| rhythm.get(previous))
+            .and_then(|previous| previous.tuplet);
+        if let Some(tuplet) = beat.tuplet.filter(|_| previous_tuplet != beat.tuplet) {
+            shapes.push(Shape::Text {
+                at: (x - CELL_WIDTH / 2.0, top + RHYTHM_HEIGHT - 1.0),
+                text: tuplet.to_string(),
+                role: Role::Technique,
+                background: false,
+            });
+        }
+    }
+}
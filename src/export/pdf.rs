@@ -0,0 +1,326 @@
+use std::fmt::Write;
+
+use super::{Page, Shape, CELL_WIDTH, FONT_SIZE};
+use crate::layout::{self, Role};
+
+/// Height of the song title block on the first page
+pub const TITLE_HEIGHT: f32 = 50.0;
+/// Height of the track name on the first page of every track
+pub const TRACK_HEIGHT: f32 = 24.0;
+/// Height of the header with the song title and the track on every page
+pub const HEADER_HEIGHT: f32 = 16.0;
+
+/// Names printed around the tab.
+pub struct Titles {
+    pub title: String,
+    /// Artist, album and tab author
+    pub subtitle: String,
+}
+
+/// A track and its pages of shapes.
+pub struct TrackPages {
+    pub name: String,
+    pub pages: Vec<Vec<Shape>>,
+}
+
+/// Every page of the tracks as a PDF file. The first page starts with the title block, every
+/// track with its name, and every page has a header and its number at the bottom. `page` must
+/// leave room for them with `HEADER_HEIGHT`, `TITLE_HEIGHT` and `TRACK_HEIGHT`.
+///
+/// Only the standard Courier fonts are used, so nothing has to be embedded. Characters they
+/// lack are replaced as in plain text.
+pub fn document(titles: &Titles, tracks: &[TrackPages], page: &Page) -> Vec<u8> {
+    let total = tracks.iter().map(|track| track.pages.len()).sum::<usize>();
+    let mut contents = Vec::new();
+    for (track_num, track) in tracks.iter().enumerate() {
+        for (page_num, shapes) in track.pages.iter().enumerate() {
+            let mut content = Content::new(page);
+            let mut top = page.margin;
+            content.text((page.margin, top + 8.0), &titles.title, Font::Regular, 8.0);
+            content.text_right(
+                (page.width - page.margin, top + 8.0),
+                &track.name,
+                Font::Regular,
+                8.0,
+            );
+            top += HEADER_HEIGHT;
+            if track_num == 0 && page_num == 0 {
+                content.text_centered(
+                    (page.width / 2.0, top + 20.0),
+                    &titles.title,
+                    Font::Bold,
+                    20.0,
+                );
+                content.text_centered(
+                    (page.width / 2.0, top + 38.0),
+                    &titles.subtitle,
+                    Font::Regular,
+                    10.0,
+                );
+                top += TITLE_HEIGHT;
+            }
+            if page_num == 0 {
+                content.text((page.margin, top + 14.0), &track.name, Font::Bold, 12.0);
+            }
+            let number = format!("{} / {}", contents.len() + 1, total);
+            content.text_centered(
+                (page.width / 2.0, page.height - page.margin / 2.0),
+                &number,
+                Font::Regular,
+                8.0,
+            );
+            for shape in shapes {
+                content.shape(shape);
+            }
+            contents.push(content.ops);
+        }
+    }
+    write_file(&contents, page)
+}
+
+#[derive(Clone, Copy)]
+enum Font {
+    Regular,
+    Bold,
+}
+
+impl Font {
+    fn name(self) -> &'static str {
+        match self {
+            Font::Regular => "F1",
+            Font::Bold => "F2",
+        }
+    }
+}
+
+/// Drawing operators of a page, with `y` going down from the top as in the shapes.
+struct Content {
+    ops: String,
+    height: f32,
+}
+
+impl Content {
+    fn new(page: &Page) -> Self {
+        Content {
+            ops: String::from("0 G 0 g 1 J\n"),
+            height: page.height,
+        }
+    }
+
+    fn y(&self, y: f32) -> f32 {
+        number(self.height - y)
+    }
+
+    fn text(&mut self, at: (f32, f32), text: &str, font: Font, size: f32) {
+        writeln!(
+            self.ops,
+            "BT /{} {} Tf {} {} Td ({}) Tj ET",
+            font.name(),
+            number(size),
+            number(at.0),
+            self.y(at.1),
+            pdf_string(text)
+        )
+        .unwrap();
+    }
+
+    /// Text ending at `at`, Courier is 0.6 of the font size wide
+    fn text_right(&mut self, at: (f32, f32), text: &str, font: Font, size: f32) {
+        let width = text.chars().count() as f32 * size * 0.6;
+        self.text((at.0 - width, at.1), text, font, size);
+    }
+
+    fn text_centered(&mut self, at: (f32, f32), text: &str, font: Font, size: f32) {
+        let width = text.chars().count() as f32 * size * 0.6;
+        self.text((at.0 - width / 2.0, at.1), text, font, size);
+    }
+
+    fn shape(&mut self, shape: &Shape) {
+        match shape {
+            Shape::Line { from, to, width } => writeln!(
+                self.ops,
+                "{} w {} {} m {} {} l S",
+                number(*width),
+                number(from.0),
+                self.y(from.1),
+                number(to.0),
+                self.y(to.1)
+            )
+            .unwrap(),
+            Shape::Arc { from, to, height } => {
+                // The quadratic curve of the SVG as a cubic one
+                let control = ((from.0 + to.0) / 2.0, from.1.min(to.1) - height * 2.0);
+                let first = (
+                    from.0 + (control.0 - from.0) * 2.0 / 3.0,
+                    from.1 + (control.1 - from.1) * 2.0 / 3.0,
+                );
+                let second = (
+                    to.0 + (control.0 - to.0) * 2.0 / 3.0,
+                    to.1 + (control.1 - to.1) * 2.0 / 3.0,
+                );
+                writeln!(
+                    self.ops,
+                    "0.8 w {} {} m {} {} {} {} {} {} c S",
+                    number(from.0),
+                    self.y(from.1),
+                    number(first.0),
+                    self.y(first.1),
+                    number(second.0),
+                    self.y(second.1),
+                    number(to.0),
+                    self.y(to.1)
+                )
+                .unwrap();
+            }
+            Shape::Dot { center, radius } => {
+                // A circle from a curve for every quarter, going around from the right
+                let (x, y, r) = (center.0, self.height - center.1, *radius);
+                let k = r * 0.5523;
+                let points = [
+                    [(r, k), (k, r), (0.0, r)],
+                    [(-k, r), (-r, k), (-r, 0.0)],
+                    [(-r, -k), (-k, -r), (0.0, -r)],
+                    [(k, -r), (r, -k), (r, 0.0)],
+                ];
+                write!(self.ops, "{} {} m", number(x + r), number(y)).unwrap();
+                for curve in points {
+                    for (dx, dy) in curve {
+                        write!(self.ops, " {} {}", number(x + dx), number(y + dy)).unwrap();
+                    }
+                    self.ops.push_str(" c");
+                }
+                self.ops.push_str(" f\n");
+            }
+            Shape::Text {
+                at,
+                text,
+                role,
+                background,
+            } => {
+                if *background {
+                    writeln!(
+                        self.ops,
+                        "1 g {} {} {} {} re f 0 g",
+                        number(at.0),
+                        self.y(at.1 + FONT_SIZE * 0.2),
+                        number(text.chars().count() as f32 * CELL_WIDTH),
+                        number(FONT_SIZE)
+                    )
+                    .unwrap();
+                }
+                let (color, font) = match role {
+                    Role::Technique => ("0.27 g", Font::Regular),
+                    Role::Marker => ("0 g", Font::Bold),
+                    Role::Highlight | Role::Selection => ("0 0.45 0.8 rg", Font::Regular),
+                    _ => ("0 g", Font::Regular),
+                };
+                self.ops.push_str(color);
+                self.ops.push(' ');
+                self.text(*at, text, font, FONT_SIZE);
+                self.ops.push_str("0 g\n");
+            }
+        }
+    }
+}
+
+/// Objects of the file: the catalog, the page tree, the two fonts, then the content and the
+/// page object of every page.
+fn write_file(contents: &[String], page: &Page) -> Vec<u8> {
+    let page_object = |page_num: usize| 6 + 2 * page_num;
+    let kids: Vec<String> = (0..contents.len())
+        .map(|page_num| format!("{} 0 R", page_object(page_num)))
+        .collect();
+    let mut objects = vec![
+        String::from("<< /Type /Catalog /Pages 2 0 R >>"),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids.join(" "),
+            contents.len()
+        ),
+        font_object("Courier"),
+        font_object("Courier-Bold"),
+    ];
+    for (page_num, content) in contents.iter().enumerate() {
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{}endstream",
+            content.len(),
+            content
+        ));
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+            number(page.width),
+            number(page.height),
+            page_object(page_num) - 1
+        ));
+    }
+
+    let mut file = String::from("%PDF-1.4\n");
+    let mut offsets = Vec::new();
+    for (object_num, object) in objects.iter().enumerate() {
+        offsets.push(file.len());
+        write!(file, "{} 0 obj\n{}\nendobj\n", object_num + 1, object).unwrap();
+    }
+    let xref = file.len();
+    write!(file, "xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1).unwrap();
+    for offset in offsets {
+        writeln!(file, "{:010} 00000 n ", offset).unwrap();
+    }
+    write!(
+        file,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+        objects.len() + 1,
+        xref
+    )
+    .unwrap();
+    file.into_bytes()
+}
+
+fn font_object(name: &str) -> String {
+    format!(
+        "<< /Type /Font /Subtype /Type1 /BaseFont /{} /Encoding /WinAnsiEncoding >>",
+        name
+    )
+}
+
+/// Text as a PDF string in the Windows-1252 encoding of the fonts, written with ASCII only.
+fn pdf_string(text: &str) -> String {
+    let mut result = String::new();
+    for ch in text.chars() {
+        let byte = win_ansi(ch)
+            .or_else(|| {
+                let plain = layout::plain_text(&ch.to_string());
+                plain.chars().next().and_then(win_ansi)
+            })
+            .unwrap_or(b'?');
+        match byte {
+            b'(' | b')' | b'\\' => {
+                result.push('\\');
+                result.push(byte as char);
+            }
+            0x20..=0x7e => result.push(byte as char),
+            _ => write!(result, "\\{:03o}", byte).unwrap(),
+        }
+    }
+    result
+}
+
+fn win_ansi(ch: char) -> Option<u8> {
+    match ch {
+        ' '..='~' | '\u{a0}'..='\u{ff}' => Some(ch as u8),
+        '€' => Some(0x80),
+        '…' => Some(0x85),
+        '‘' => Some(0x91),
+        '’' => Some(0x92),
+        '“' => Some(0x93),
+        '”' => Some(0x94),
+        '•' => Some(0x95),
+        '–' => Some(0x96),
+        '—' => Some(0x97),
+        _ => None,
+    }
+}
+
+/// A coordinate rounded to a hundredth of a point
+fn number(value: f32) -> f32 {
+    (value * 100.0).round() / 100.0
+}
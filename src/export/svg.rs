@@ -0,0 +1,100 @@
+use std::fmt::Write;
+
+use super::{Page, Shape, CELL_WIDTH, FONT_SIZE};
+use crate::layout::Role;
+
+/// A page of shapes as an SVG document.
+pub fn document(page: &Page, shapes: &[Shape]) -> String {
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{w}pt" height="{h}pt" viewBox="0 0 {w} {h}">"#,
+        w = number(page.width),
+        h = number(page.height)
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        "<style>text{{font-family:monospace;font-size:{}px;white-space:pre}} line,path{{stroke:#000}} path{{fill:none}} circle{{fill:#000}} .technique{{fill:#444}} .marker{{font-weight:bold}} .highlight{{fill:#07c}}</style>",
+        number(FONT_SIZE)
+    )
+    .unwrap();
+    writeln!(svg, r##"<rect width="100%" height="100%" fill="#fff"/>"##).unwrap();
+    for shape in shapes {
+        match shape {
+            Shape::Line { from, to, width } => writeln!(
+                svg,
+                r#"<line x1="{}" y1="{}" x2="{}" y2="{}" stroke-width="{}"/>"#,
+                number(from.0),
+                number(from.1),
+                number(to.0),
+                number(to.1),
+                number(*width)
+            ),
+            Shape::Arc { from, to, height } => writeln!(
+                svg,
+                r#"<path d="M{} {} Q{} {} {} {}" stroke-width="0.8"/>"#,
+                number(from.0),
+                number(from.1),
+                number((from.0 + to.0) / 2.0),
+                number(from.1.min(to.1) - height * 2.0),
+                number(to.0),
+                number(to.1)
+            ),
+            Shape::Dot { center, radius } => writeln!(
+                svg,
+                r#"<circle cx="{}" cy="{}" r="{}"/>"#,
+                number(center.0),
+                number(center.1),
+                number(*radius)
+            ),
+            Shape::Text {
+                at,
+                text,
+                role,
+                background,
+            } => {
+                if *background {
+                    writeln!(
+                        svg,
+                        r##"<rect x="{}" y="{}" width="{}" height="{}" fill="#fff"/>"##,
+                        number(at.0),
+                        number(at.1 - FONT_SIZE * 0.8),
+                        number(text.chars().count() as f32 * CELL_WIDTH),
+                        number(FONT_SIZE)
+                    )
+                    .unwrap();
+                }
+                let class = match role {
+                    Role::Technique => r#" class="technique""#,
+                    Role::Marker => r#" class="marker""#,
+                    Role::Highlight | Role::Selection => r#" class="highlight""#,
+                    _ => "",
+                };
+                writeln!(
+                    svg,
+                    r#"<text x="{}" y="{}"{}>{}</text>"#,
+                    number(at.0),
+                    number(at.1),
+                    class,
+                    escape(text)
+                )
+            }
+        }
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// A coordinate rounded to a hundredth of a point
+fn number(value: f32) -> f32 {
+    (value * 100.0).round() / 100.0
+}
+
+/// Text with the characters XML reserves replaced.
+pub fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
@@ -0,0 +1,254 @@
+use guitarpro::beat::Beat;
+use guitarpro::enums::NoteType;
+
+use super::lyrics::place_words;
+use super::notation::pitch_name;
+use super::{Cell, Role};
+
+/// Chord suffixes with their intervals from the root, in the order they are tried.
+const CHORD_TYPES: [(&str, &[i16]); 20] = [
+    ("5", &[0, 7]),
+    ("", &[0, 4, 7]),
+    ("m", &[0, 3, 7]),
+    ("7", &[0, 4, 7, 10]),
+    ("maj7", &[0, 4, 7, 11]),
+    ("m7", &[0, 3, 7, 10]),
+    ("sus4", &[0, 5, 7]),
+    ("sus2", &[0, 2, 7]),
+    ("dim", &[0, 3, 6]),
+    ("aug", &[0, 4, 8]),
+    ("6", &[0, 4, 7, 9]),
+    ("m6", &[0, 3, 7, 9]),
+    ("m7♭5", &[0, 3, 6, 10]),
+    ("dim7", &[0, 3, 6, 9]),
+    ("7sus4", &[0, 5, 7, 10]),
+    ("add9", &[0, 2, 4, 7]),
+    ("madd9", &[0, 2, 3, 7]),
+    ("9", &[0, 2, 4, 7, 10]),
+    ("m9", &[0, 2, 3, 7, 10]),
+    ("maj9", &[0, 2, 4, 7, 11]),
+];
+
+/// Name of a chord and the fret played on every string, from the first string.
+#[derive(Clone)]
+pub struct ChordShape {
+    pub name: String,
+    /// -1 for a string that is not played
+    pub frets: Vec<i8>,
+}
+
+impl ChordShape {
+    /// Chord stored on the beat, or the one formed by its notes. The stored name is used as is,
+    /// otherwise it is found from the pitches.
+    pub fn from_beat(beat: &Beat, tuning: &[(i8, i8)]) -> Option<Self> {
+        let mut frets = vec![-1; tuning.len()];
+        let stored = beat.effect.chord.as_ref();
+        match stored.filter(|chord| !chord.strings.is_empty()) {
+            Some(chord) => {
+                for (fret, stored) in frets.iter_mut().zip(&chord.strings) {
+                    *fret = *stored;
+                }
+            }
+            None => {
+                for note in &beat.notes {
+                    if matches!(note.kind, NoteType::Rest | NoteType::Dead) {
+                        continue;
+                    }
+                    if let Some(fret) = frets.get_mut((note.string as usize).wrapping_sub(1)) {
+                        *fret = note.value as i8;
+                    }
+                }
+            }
+        }
+        let name = match stored.filter(|chord| !chord.name.is_empty()) {
+            Some(chord) => chord.name.clone(),
+            None => {
+                let pitches: Vec<i16> = frets
+                    .iter()
+                    .zip(tuning)
+                    .filter(|(fret, _)| **fret >= 0)
+                    .map(|(fret, (_, open))| *open as i16 + *fret as i16)
+                    .collect();
+                detect(&pitches)?
+            }
+        };
+        Some(ChordShape { name, frets })
+    }
+
+    /// Diagram with the lowest string on the left: `x` for muted and `o` for open strings
+    /// over the nut, `O` for fretted notes. The first fret is written on the right when the
+    /// chord is played higher up the neck.
+    pub fn diagram(&self) -> Vec<String> {
+        let strings: Vec<i8> = self.frets.iter().rev().copied().collect();
+        let fretted = strings.iter().filter(|fret| **fret > 0);
+        let lowest = fretted.clone().copied().min().unwrap_or(1);
+        let highest = fretted.copied().max().unwrap_or(1);
+        let first_fret = if highest <= 4 { 1 } else { lowest };
+        let width = (strings.len() * 2).saturating_sub(1);
+
+        let mut result = vec![format!("{: <width$}", self.name, width = width)];
+        let open: Vec<&str> = strings
+            .iter()
+            .map(|fret| match fret {
+                -1 => "x",
+                0 => "o",
+                _ => " ",
+            })
+            .collect();
+        result.push(open.join(" "));
+        result.push(if first_fret == 1 {
+            "=".repeat(width)
+        } else {
+            "-".repeat(width)
+        });
+        for fret in first_fret..first_fret + (highest - first_fret + 1).max(4) {
+            let row: Vec<&str> = strings
+                .iter()
+                .map(|played| if *played == fret { "O" } else { "|" })
+                .collect();
+            let mut row = row.join(" ");
+            if fret == first_fret && first_fret > 1 {
+                row.push_str(format!(" {}fr", first_fret).as_str());
+            }
+            result.push(row);
+        }
+        result
+    }
+}
+
+/// Chord name of a set of pitches, e.g. "Em7", "D5" or "C/E", `None` if they do not form a
+/// known chord. The lowest pitch is tried as the root first.
+pub fn detect(pitches: &[i16]) -> Option<String> {
+    let bass = *pitches.iter().min()?;
+    let mut classes: Vec<i16> = pitches.iter().map(|pitch| pitch.rem_euclid(12)).collect();
+    classes.sort();
+    classes.dedup();
+    if classes.len() < 2 {
+        return None;
+    }
+    let mut roots = classes.clone();
+    roots.sort_by_key(|root| *root != bass.rem_euclid(12));
+    // Exact matches first, then chords with the fifth left out
+    for omit_fifth in [false, true] {
+        for root in &roots {
+            let mut intervals: Vec<i16> = classes
+                .iter()
+                .map(|class| (class - root).rem_euclid(12))
+                .collect();
+            intervals.sort();
+            let found = CHORD_TYPES.iter().find(|(_, chord)| {
+                if omit_fifth {
+                    chord.len() > 3
+                        && chord.contains(&7)
+                        && chord.iter().filter(|i| **i != 7).eq(intervals.iter())
+                } else {
+                    chord.iter().eq(intervals.iter())
+                }
+            });
+            if let Some((suffix, _)) = found {
+                let mut name = format!("{}{}", pitch_name(*root), suffix);
+                if *root != bass.rem_euclid(12) {
+                    name.push('/');
+                    name.push_str(pitch_name(bass).as_str());
+                }
+                return Some(name);
+            }
+        }
+    }
+    None
+}
+
+/// A row of chord names above the staff.
+pub struct ChordsText {
+    /// `(beat, name)` in beat order
+    pub names: Vec<(usize, String)>,
+}
+
+impl ChordsText {
+    pub fn cells(&self) -> Vec<Cell> {
+        place_words(&self.names, |_| Role::Marker)
+    }
+}
+
+/// Diagrams side by side, as many on a row as fit in `width`.
+pub fn panel(shapes: &[ChordShape], width: usize) -> Vec<String> {
+    let diagrams: Vec<Vec<String>> = shapes.iter().map(ChordShape::diagram).collect();
+    let mut rows: Vec<String> = Vec::new();
+    let mut row: Vec<&Vec<String>> = Vec::new();
+    let mut row_width = 0;
+    let diagram_width = |diagram: &Vec<String>| {
+        diagram
+            .iter()
+            .map(|line| line.chars().count())
+            .max()
+            .unwrap_or(0)
+    };
+    let flush = |row: &mut Vec<&Vec<String>>, rows: &mut Vec<String>| {
+        let height = row.iter().map(|diagram| diagram.len()).max().unwrap_or(0);
+        for line_num in 0..height {
+            let line: Vec<String> = row
+                .iter()
+                .map(|diagram| {
+                    let line = diagram.get(line_num).map_or("", |line| line.as_str());
+                    format!("{: <width$}", line, width = diagram_width(diagram))
+                })
+                .collect();
+            rows.push(line.join("   "));
+        }
+        rows.push(String::new());
+        row.clear();
+    };
+    for diagram in &diagrams {
+        let width_needed = diagram_width(diagram) + 3;
+        if !row.is_empty() && row_width + width_needed > width.saturating_sub(4) {
+            flush(&mut row, &mut rows);
+            row_width = 0;
+        }
+        row.push(diagram);
+        row_width += width_needed;
+    }
+    if !row.is_empty() {
+        flush(&mut row, &mut rows);
+    }
+    rows.pop();
+    if rows.is_empty() {
+        rows.push(String::from("No chords"));
+    }
+    rows
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seventh_chords() {
+        // E2 B2 D3 G3 B3 E4, open Em7
+        assert_eq!(detect(&[40, 47, 50, 55, 59, 64]).as_deref(), Some("Em7"));
+        // B3 D4 F4 A4, with the bass as the root rather than Dm6
+        assert_eq!(detect(&[59, 62, 65, 69]).as_deref(), Some("Bm7♭5"));
+    }
+
+    #[test]
+    fn power_chord() {
+        assert_eq!(detect(&[50, 57, 62]).as_deref(), Some("D5"));
+    }
+
+    #[test]
+    fn slash_chord() {
+        // C major over E
+        assert_eq!(detect(&[40, 48, 55, 64]).as_deref(), Some("C/E"));
+    }
+
+    #[test]
+    fn chord_without_the_fifth() {
+        // C3 E3 A♯3
+        assert_eq!(detect(&[48, 52, 58]).as_deref(), Some("C7"));
+    }
+
+    #[test]
+    fn octaves_are_no_chord() {
+        assert_eq!(detect(&[40, 52]), None);
+        assert_eq!(detect(&[]), None);
+    }
+}
@@ -0,0 +1,285 @@
+use guitarpro::beat::Beat;
+use guitarpro::effects::{BendEffect, HarmonicEffect};
+use guitarpro::enums::{BendType, HarmonicType, Octave, SlapEffect};
+use guitarpro::note::Note;
+
+use super::{cells, Cell, MeasureLayout, Role};
+
+const BEAT_WIDTH: usize = MeasureLayout::BEAT_WIDTH;
+const SUPERSCRIPT_DIGITS: [char; 10] = ['⁰', '¹', '²', '³', '⁴', '⁵', '⁶', '⁷', '⁸', '⁹'];
+
+/// A row of short labels drawn above the strings, one cell per beat.
+pub struct LaneText {
+    pub cells: Vec<String>,
+}
+
+impl LaneText {
+    pub fn cells(&self) -> Vec<Cell> {
+        let mut result = String::from(" ");
+        for cell in &self.cells {
+            let cell: String = cell.chars().take(BEAT_WIDTH).collect();
+            result.push_str(format!("{: <BEAT_WIDTH$}", cell).as_str());
+        }
+        cells(&result, Role::Technique)
+    }
+
+    /// Lane with a line over every run of beats matching `is_on`, `None` if no beat does.
+    /// The line starts with `label` and, if given, is closed by `end`.
+    pub fn span(
+        beats: &[Beat],
+        is_on: impl Fn(&Beat) -> bool,
+        label: &str,
+        fill: char,
+        end: Option<char>,
+    ) -> Option<Self> {
+        let flags: Vec<bool> = beats.iter().map(is_on).collect();
+        if !flags.contains(&true) {
+            return None;
+        }
+        let label: Vec<char> = label.chars().collect();
+        let mut line = vec![' '; beats.len() * BEAT_WIDTH];
+        let mut beat_num = 0;
+        while beat_num < flags.len() {
+            if !flags[beat_num] {
+                beat_num += 1;
+                continue;
+            }
+            let start = beat_num * BEAT_WIDTH + 1;
+            while beat_num < flags.len() && flags[beat_num] {
+                beat_num += 1;
+            }
+            let stop = match end {
+                Some(_) => (beat_num * BEAT_WIDTH - 1).max(start + label.len()),
+                None => beat_num * BEAT_WIDTH - 1,
+            };
+            for col in start..=stop {
+                let ch = match label.get(col - start) {
+                    Some(ch) => *ch,
+                    None if col == stop => end.unwrap_or(fill),
+                    None => fill,
+                };
+                if let Some(cell) = line.get_mut(col) {
+                    *cell = ch;
+                }
+            }
+        }
+        Some(LaneText {
+            cells: line
+                .chunks(BEAT_WIDTH)
+                .map(|cell| cell.iter().collect())
+                .collect(),
+        })
+    }
+
+    /// Lane with the text returned by `mark` for every beat, `None` if all of them are empty.
+    /// The text starts above the fret number and is moved left when it would not fit.
+    pub fn marks(beats: &[Beat], mark: impl Fn(&Beat) -> String) -> Option<Self> {
+        let cells: Vec<String> = beats
+            .iter()
+            .map(|beat| {
+                let text = mark(beat);
+                let indent = BEAT_WIDTH.saturating_sub(text.chars().count()).min(2);
+                format!("{}{}", " ".repeat(indent), text)
+            })
+            .collect();
+        if cells.iter().all(|cell| cell.trim().is_empty()) {
+            None
+        } else {
+            Some(LaneText { cells })
+        }
+    }
+
+    /// Lane with accent (`>`), heavy accent (`^`) and staccato (`·`) marks.
+    pub fn articulations(beats: &[Beat]) -> Option<Self> {
+        Self::marks(beats, |beat| {
+            let mut marks = String::new();
+            if beat.notes.iter().any(|n| n.effect.heavy_accentuated_note) {
+                marks.push('^');
+            } else if beat.notes.iter().any(|n| n.effect.accentuated_note) {
+                marks.push('>');
+            }
+            if beat.notes.iter().any(|n| n.effect.staccato) {
+                marks.push('·');
+            }
+            marks
+        })
+    }
+
+    /// Lane with tapping (`T`), slap (`S`), pop (`P`) and trill (`tr(fret)`) marks.
+    pub fn techniques(beats: &[Beat]) -> Option<Self> {
+        Self::marks(beats, |beat| {
+            let mut marks = String::from(match beat.effect.slap_effect {
+                SlapEffect::None => "",
+                SlapEffect::Tapping => "T",
+                SlapEffect::Slapping => "S",
+                SlapEffect::Popping => "P",
+            });
+            if let Some(trill) = beat.notes.iter().find_map(|n| n.effect.trill.as_ref()) {
+                marks.push_str(format!("tr({})", trill.fret).as_str());
+            }
+            if let Some(tremolo) = beat
+                .notes
+                .iter()
+                .find_map(|n| n.effect.tremolo_picking.as_ref())
+            {
+                // One slash per beam: eighth, sixteenth, thirty-second
+                let slashes = (tremolo.duration.value / 4).max(2).ilog2();
+                marks.push_str("/".repeat(slashes as usize).as_str());
+            }
+            marks
+        })
+    }
+
+    /// Lane with the kind of harmonic, plus the harmonic fret for artificial and tapped ones.
+    pub fn harmonics(beats: &[Beat], tuning: &[(i8, i8)]) -> Option<Self> {
+        Self::marks(beats, |beat| {
+            beat.notes
+                .iter()
+                .find_map(|note| {
+                    let harmonic = note.effect.harmonic.as_ref()?;
+                    Some(match harmonic_fret(note, harmonic, tuning) {
+                        Some(fret) => format!("{}({})", harmonic_label(harmonic), fret),
+                        None => harmonic_label(harmonic).to_string(),
+                    })
+                })
+                .unwrap_or_default()
+        })
+    }
+
+    /// Lane with the amount of the biggest bend of every beat, `None` if the measure has no bends.
+    /// Fractions are written with ASCII digits unless `unicode` is set.
+    pub fn bends(beats: &[Beat], unicode: bool) -> Option<Self> {
+        Self::marks(beats, |beat| {
+            beat.notes
+                .iter()
+                .filter_map(|note| note.effect.bend.as_ref())
+                .map(bend_amount)
+                .max()
+                .map_or(String::new(), |amount| bend_label(amount, unicode))
+        })
+    }
+}
+
+/// Highest point of the bend in quarter tones.
+pub fn bend_amount(bend: &BendEffect) -> i8 {
+    bend.points
+        .iter()
+        .map(|point| point.value)
+        .max()
+        .unwrap_or(0)
+}
+
+/// "full" for a whole tone, fractions otherwise: "¼", "½", "1½", "2"... or "1/4", "1/2",
+/// "1 1/2" without `unicode`.
+pub fn bend_label(quarter_tones: i8, unicode: bool) -> String {
+    let whole = quarter_tones / 4;
+    let fraction = match (quarter_tones % 4, unicode) {
+        (1, true) => "¼",
+        (2, true) => "½",
+        (3, true) => "¾",
+        (1, false) => "1/4",
+        (2, false) => "1/2",
+        (3, false) => "3/4",
+        _ => "",
+    };
+    match (whole, fraction) {
+        (1, "") => String::from("full"),
+        (0, _) => String::from(fraction),
+        (_, "") => whole.to_string(),
+        _ if unicode => format!("{}{}", whole, fraction),
+        _ => format!("{} {}", whole, fraction),
+    }
+}
+
+/// Shape of the bend. Custom bends have no preset, so it is guessed from the points.
+pub fn bend_type(bend: &BendEffect) -> BendType {
+    if bend.kind != BendType::None {
+        return bend.kind.clone();
+    }
+    let mut values: Vec<i8> = bend.points.iter().map(|point| point.value).collect();
+    values.dedup();
+    let first = values.first().copied().unwrap_or(0);
+    let last = values.last().copied().unwrap_or(0);
+    let turns = values
+        .windows(3)
+        .filter(|w| (w[1] > w[0]) != (w[2] > w[1]))
+        .count();
+    if first > 0 {
+        if last < first {
+            BendType::PrebendRelease
+        } else {
+            BendType::Prebend
+        }
+    } else {
+        match turns {
+            0 => BendType::Bend,
+            1 => BendType::BendRelease,
+            _ => BendType::BendReleaseBend,
+        }
+    }
+}
+
+/// Arrows drawn right after the fret number.
+pub fn bend_glyphs(bend: &BendEffect) -> &'static str {
+    match bend_type(bend) {
+        BendType::BendRelease => "↗↘",
+        BendType::BendReleaseBend => "↗↘↗",
+        BendType::Prebend => "↑",
+        BendType::PrebendRelease => "↑↘",
+        _ => "↗",
+    }
+}
+
+pub fn harmonic_label(harmonic: &HarmonicEffect) -> &'static str {
+    match harmonic.kind {
+        HarmonicType::Natural => "NH",
+        HarmonicType::Artificial => "AH",
+        HarmonicType::Tapped => "TH",
+        HarmonicType::Pinch => "PH",
+        HarmonicType::Semi => "SH",
+    }
+}
+
+/// Symbol drawn in front of the fret number.
+pub fn harmonic_glyph(harmonic: &HarmonicEffect) -> &'static str {
+    match harmonic.kind {
+        HarmonicType::Natural => "◇",
+        HarmonicType::Artificial => "◈",
+        HarmonicType::Tapped => "◆",
+        HarmonicType::Pinch => "◊",
+        HarmonicType::Semi => "⋄",
+    }
+}
+
+/// Fret where the harmonic is touched: the tapped fret, or for artificial harmonics the node
+/// above the fretted note that sounds the stored pitch.
+pub fn harmonic_fret(note: &Note, harmonic: &HarmonicEffect, tuning: &[(i8, i8)]) -> Option<i16> {
+    match harmonic.kind {
+        HarmonicType::Tapped => harmonic.fret.map(i16::from),
+        HarmonicType::Artificial => {
+            let open = tuning.get((note.string as usize).checked_sub(1)?)?.1 as i16;
+            let pitch = harmonic.pitch.as_ref()?;
+            let interval = (pitch.value as i16 - open - note.value).rem_euclid(12);
+            let node = match (interval, &harmonic.octave) {
+                (0, Some(Octave::Quindicesima)) => 5,
+                (7, _) => 7,
+                (4, _) => 4,
+                _ => 12,
+            };
+            Some(note.value + node)
+        }
+        _ => None,
+    }
+}
+
+/// Fret number of a grace note in small digits, `ˣ` for a dead grace note.
+pub fn grace_text(fret: i8, is_dead: bool) -> String {
+    if is_dead {
+        return String::from("ˣ");
+    }
+    fret.to_string()
+        .chars()
+        .filter_map(|digit| digit.to_digit(10))
+        .map(|digit| SUPERSCRIPT_DIGITS[digit as usize])
+        .collect()
+}
@@ -0,0 +1,123 @@
+use guitarpro::enums::NoteType;
+use guitarpro::track::Track;
+
+use super::voices;
+use super::{cells, Cell, MeasureLayout, Role};
+
+/// A syllable sung on a beat.
+pub struct Syllable {
+    pub measure: usize,
+    /// Start of the beat from the start of the measure, in ticks
+    pub start: i64,
+    pub text: String,
+}
+
+/// Splits a lyrics line into syllables and puts them on the beats with notes of the first
+/// `visible` voice, from `first_measure` on (counted from 1). Syllables are separated by spaces or after a dash,
+/// `+` puts two syllables on the same beat and text in `[]` is not sung.
+pub fn syllables(text: &str, first_measure: u16, track: &Track, visible: &[bool]) -> Vec<Syllable> {
+    let mut sung = String::new();
+    let mut depth = 0;
+    for ch in text.chars() {
+        match ch {
+            '[' => depth += 1,
+            ']' => depth = (depth - 1).max(0),
+            _ if depth == 0 => sung.push(ch),
+            _ => (),
+        }
+    }
+    let mut words = sung
+        .split_whitespace()
+        .flat_map(|word| word.split_inclusive('-'))
+        .map(|syllable| syllable.replace('+', " "));
+
+    let mut result = Vec::new();
+    let first_measure = (first_measure as usize).saturating_sub(1);
+    for (measure_num, measure) in track.measures.iter().enumerate().skip(first_measure) {
+        let voice = (0..measure.voices.len())
+            .find(|voice| visible.get(*voice).copied().unwrap_or(true))
+            .map(|voice| &measure.voices[voice]);
+        let Some(voice) = voice else {
+            continue;
+        };
+        for beat in voices::with_offsets(&voice.beats) {
+            // Rests and held notes are not sung
+            let is_sung = beat
+                .notes
+                .iter()
+                .any(|note| note.kind != NoteType::Rest && note.kind != NoteType::Tie);
+            if !is_sung {
+                continue;
+            }
+            let Some(text) = words.next() else {
+                return result;
+            };
+            result.push(Syllable {
+                measure: measure_num,
+                start: beat.start.unwrap_or(0),
+                text,
+            });
+        }
+    }
+    result
+}
+
+/// A row of syllables under the strings.
+pub struct LyricsText {
+    /// `(beat, syllable)` in beat order
+    pub syllables: Vec<(usize, String)>,
+    /// Syllable being sung during playback
+    pub current: Option<usize>,
+}
+
+impl LyricsText {
+    pub fn cells(&self) -> Vec<Cell> {
+        place_words(&self.syllables, |syllable_num| {
+            if self.current == Some(syllable_num) {
+                Role::Highlight
+            } else {
+                Role::Text
+            }
+        })
+    }
+}
+
+/// Line with every `(beat, word)` starting above the fret number of the beat, or after the
+/// previous word when that one is too long. `role` gives the role of a word from its index.
+pub fn place_words(words: &[(usize, String)], role: impl Fn(usize) -> Role) -> Vec<Cell> {
+    let mut result = Vec::new();
+    for (word_num, (beat, text)) in words.iter().enumerate() {
+        let width = result.len();
+        let column = MeasureLayout::fret_column(*beat).max(if width == 0 { 0 } else { width + 1 });
+        result.extend(cells(&" ".repeat(column - width), Role::Text));
+        result.extend(cells(text, role(word_num)));
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use guitarpro::measure::Measure;
+
+    use super::*;
+    use crate::layout::voices::tests::voice;
+
+    #[test]
+    fn lyrics_follow_the_first_visible_voice() {
+        let track = Track {
+            measures: vec![Measure {
+                voices: vec![voice(1, &[2, 2]), voice(6, &[4, 4, 4, 4])],
+                ..Measure::default()
+            }],
+            ..Track::default()
+        };
+        let starts = |visible: &[bool]| -> Vec<i64> {
+            syllables("a b c d", 1, &track, visible)
+                .iter()
+                .map(|syllable| syllable.start)
+                .collect()
+        };
+        assert_eq!(starts(&[true, true]), [0, 1920]);
+        assert_eq!(starts(&[false, true]), [0, 960, 1920, 2880]);
+    }
+}
@@ -0,0 +1,902 @@
+pub mod chords;
+pub mod effects;
+pub mod lyrics;
+pub mod notation;
+pub mod playback;
+pub mod roadmap;
+pub mod voices;
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::Range;
+
+use chords::{ChordShape, ChordsText};
+use effects::LaneText;
+use guitarpro::beat::Beat;
+use guitarpro::effects::{BendEffect, GraceEffect, HarmonicEffect};
+use guitarpro::enums::{NoteType, SlideType};
+use guitarpro::headers::MeasureHeader;
+use guitarpro::track::Track;
+use lyrics::{LyricsText, Syllable};
+use notation::{Clef, StaffColumn, StaffText};
+use voices::MeasureBeats;
+
+/// What a cell shows, for the renderer to pick its style.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum Role {
+    #[default]
+    Text,
+    /// Fret numbers and note heads
+    Fret,
+    /// Slides, bends, hammer-ons and the effect lanes
+    Technique,
+    /// Strings, bar lines and staff lines
+    BarLine,
+    Selection,
+    /// Notes of the highlighted voice
+    Highlight,
+    Marker,
+    /// Measures whose beats do not fill the time signature
+    Warning,
+}
+
+/// A character with the combining marks drawn over it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Cell {
+    pub glyph: String,
+    pub role: Role,
+}
+
+impl Cell {
+    pub fn new(glyph: impl Into<String>, role: Role) -> Self {
+        Cell {
+            glyph: glyph.into(),
+            role,
+        }
+    }
+}
+
+/// Cells of `text`, a combining mark goes in the cell of the character before it.
+pub fn cells(text: &str, role: Role) -> Vec<Cell> {
+    let mut result: Vec<Cell> = Vec::new();
+    for ch in text.chars() {
+        match result.last_mut() {
+            Some(cell) if is_combining(ch) => cell.glyph.push(ch),
+            _ => result.push(Cell::new(ch, role)),
+        }
+    }
+    result
+}
+
+fn is_combining(ch: char) -> bool {
+    ('\u{0300}'..='\u{036f}').contains(&ch) || ('\u{fe20}'..='\u{fe2f}').contains(&ch)
+}
+
+/// Runs of cells with the same role as `(role, text)`, to style them together.
+pub fn runs(row: &[Cell]) -> Vec<(Role, String)> {
+    let mut result: Vec<(Role, String)> = Vec::new();
+    for cell in row {
+        match result.last_mut() {
+            Some((role, text)) if *role == cell.role => text.push_str(&cell.glyph),
+            _ => result.push((cell.role, cell.glyph.clone())),
+        }
+    }
+    result
+}
+
+/// Rows of cells.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Grid {
+    pub rows: Vec<Vec<Cell>>,
+}
+
+impl Grid {
+    pub fn width(&self) -> usize {
+        self.rows.iter().map(Vec::len).max().unwrap_or(0)
+    }
+
+    /// Every row as text, without the roles.
+    pub fn text(&self) -> Vec<String> {
+        self.rows
+            .iter()
+            .map(|row| row.iter().map(|cell| cell.glyph.as_str()).collect())
+            .collect()
+    }
+}
+
+impl fmt::Display for Grid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for line in self.text() {
+            writeln!(f, "{}", line)?;
+        }
+        Ok(())
+    }
+}
+
+/// Text with box drawing and other glyphs replaced with ASCII, and the combining marks left
+/// out.
+pub fn plain_text(text: &str) -> String {
+    text.chars()
+        // Combining half marks used for ties and slurs
+        .filter(|ch| !('\u{fe20}'..='\u{fe2f}').contains(ch))
+        .map(ascii)
+        .collect()
+}
+
+fn ascii(ch: char) -> char {
+    match ch {
+        '─' => '-',
+        '│' | '╭' | '├' | '╰' | '╓' | '╟' | '╙' | '╖' | '╢' | '╜' => '|',
+        '┌' | '┐' | '└' | '┘' => '+',
+        '•' => '*',
+        '×' => 'x',
+        '╱' => '/',
+        '╲' => '\\',
+        '●' => '@',
+        '○' => 'o',
+        '♯' => '#',
+        '♭' => 'b',
+        '↗' | '↑' => '^',
+        '…' => '~',
+        '↘' => 'v',
+        '◇' | '◈' | '◆' | '◊' | '⋄' => '<',
+        '·' => '.',
+        'ˣ' => 'x',
+        '⁰' => '0',
+        '¹' => '1',
+        '²' => '2',
+        '³' => '3',
+        '⁴' => '4',
+        '⁵' => '5',
+        '⁶' => '6',
+        '⁷' => '7',
+        '⁸' => '8',
+        '⁹' => '9',
+        '𝄞' => 'G',
+        '𝄢' => 'F',
+        _ => ch,
+    }
+}
+
+/// Settings that change how every measure is laid out.
+#[derive(Clone, PartialEq)]
+pub struct LayoutOptions {
+    /// Standard notation above the tab
+    pub show_notation: bool,
+    pub visible_voices: Vec<bool>,
+    pub highlighted_voice: Option<usize>,
+    /// The cursor selects a single string instead of the whole beat
+    pub edit_mode: bool,
+    /// When false, box drawing and other glyphs are replaced with ASCII
+    pub unicode: bool,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        LayoutOptions {
+            show_notation: true,
+            visible_voices: vec![true; 2],
+            highlighted_voice: None,
+            edit_mode: false,
+            unicode: true,
+        }
+    }
+}
+
+/// Position drawn as selected.
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub measure: usize,
+    pub beat: usize,
+    pub string: usize,
+    /// The syllable sung at the cursor is highlighted during playback
+    pub playing: bool,
+}
+
+/// Duration of a beat, for renderers that draw the rhythm.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Rhythm {
+    /// 1 for a whole note, 2 for a half note, 4 for a quarter note and so on
+    pub value: u16,
+    pub dotted: bool,
+    /// Notes in a tuplet, e.g. 3 for a triplet
+    pub tuplet: Option<u8>,
+    pub rest: bool,
+}
+
+impl Rhythm {
+    pub fn new(beat: &Beat) -> Self {
+        let duration = &beat.duration;
+        Rhythm {
+            value: duration.value,
+            dotted: duration.dotted || duration.double_dotted,
+            tuplet: (duration.tuplet_enters != duration.tuplet_times)
+                .then_some(duration.tuplet_enters),
+            rest: beat.notes.is_empty(),
+        }
+    }
+}
+
+/// Layout of a measure: its title, chord names, staff, effect lanes, strings and lyrics.
+pub struct MeasureLayout {
+    pub grid: Grid,
+    /// Rows of the strings, from the first string
+    pub strings: Range<usize>,
+    /// Duration of every beat, in the columns given by `beat_column`
+    pub rhythm: Vec<Rhythm>,
+}
+
+impl MeasureLayout {
+    /// Columns every beat is drawn in
+    pub const BEAT_WIDTH: usize = 6;
+
+    /// First column of the beat `beat_num`, after the bar line.
+    pub fn beat_column(beat_num: usize) -> usize {
+        1 + beat_num * Self::BEAT_WIDTH
+    }
+
+    /// Column of the first fret digit of the beat `beat_num`.
+    pub fn fret_column(beat_num: usize) -> usize {
+        Self::beat_column(beat_num) + 2
+    }
+
+    /// Beat drawn at `column`, the bar line counting as the first beat.
+    pub fn beat_at_column(column: usize) -> usize {
+        column.saturating_sub(1) / Self::BEAT_WIDTH
+    }
+}
+
+/// Lays out the measures of a track.
+pub struct TabLayout<'a> {
+    pub track: &'a Track,
+    pub headers: &'a [MeasureHeader],
+    /// Syllables of the lyrics lines sung on this track
+    pub lyrics: &'a [Vec<Syllable>],
+    pub options: LayoutOptions,
+    pub cursor: Option<Cursor>,
+}
+
+impl<'a> TabLayout<'a> {
+    pub fn new(track: &'a Track, headers: &'a [MeasureHeader]) -> Self {
+        TabLayout {
+            track,
+            headers,
+            lyrics: &[],
+            options: LayoutOptions::default(),
+            cursor: None,
+        }
+    }
+
+    pub fn measure_beats(&self, measure_num: usize) -> MeasureBeats {
+        MeasureBeats::new(
+            &self.track.measures[measure_num],
+            &self.options.visible_voices,
+        )
+    }
+
+    /// Cursor in `measure_num`, if it is there
+    fn cursor_in(&self, measure_num: usize) -> Option<&Cursor> {
+        self.cursor
+            .as_ref()
+            .filter(|cursor| cursor.measure == measure_num)
+    }
+
+    fn staff_text(&self, measure_num: usize) -> Option<StaffText> {
+        if !self.options.show_notation || self.track.percussion_track {
+            return None;
+        }
+        let measure = &self.track.measures[measure_num];
+        let use_flats = measure.key_signature.key < 0;
+        let mut columns: Vec<StaffColumn> = self
+            .measure_beats(measure_num)
+            .beats
+            .iter()
+            .map(|beat| StaffColumn::new(beat, &self.track.strings, use_flats))
+            .collect();
+        if let Some(cursor) = self.cursor_in(measure_num) {
+            if let Some(column) = columns.get_mut(cursor.beat) {
+                column.is_selected = true;
+            }
+        }
+        Some(StaffText {
+            clef: Clef::for_tuning(&self.track.strings),
+            columns,
+        })
+    }
+
+    fn lanes(&self, measure_num: usize) -> Vec<LaneText> {
+        let beats = &self.measure_beats(measure_num).beats;
+        let alternative = self
+            .headers
+            .get(measure_num)
+            .map_or(0, |header| header.repeat_alternative);
+        let alternative_label = match measure_num.checked_sub(1).and_then(|m| self.headers.get(m)) {
+            // The bracket goes on from the previous measure
+            Some(previous) if previous.repeat_alternative == alternative => String::new(),
+            _ => format!("┌{}", roadmap::alternative_label(alternative)),
+        };
+        [
+            LaneText::span(
+                beats,
+                |_| alternative != 0,
+                alternative_label.as_str(),
+                '─',
+                None,
+            ),
+            LaneText::span(
+                beats,
+                |beat| beat.notes.iter().any(|note| note.effect.let_ring),
+                "let ring",
+                '-',
+                Some('|'),
+            ),
+            LaneText::span(
+                beats,
+                |beat| beat.notes.iter().any(|note| note.effect.palm_mute),
+                "P.M.",
+                '-',
+                Some('|'),
+            ),
+            LaneText::span(
+                beats,
+                |beat| beat.effect.vibrato || beat.notes.iter().any(|note| note.effect.vibrato),
+                "",
+                '~',
+                None,
+            ),
+            LaneText::articulations(beats),
+            LaneText::techniques(beats),
+            LaneText::harmonics(beats, &self.track.strings),
+            LaneText::bends(beats, self.options.unicode),
+        ]
+        .into_iter()
+        .flatten()
+        .collect()
+    }
+
+    /// A row for every lyrics line with syllables in the measure. During playback the syllable
+    /// being sung is highlighted.
+    fn lyrics_text(&self, measure_num: usize) -> Vec<LyricsText> {
+        let beats = self.measure_beats(measure_num).beats;
+        let playing_beat = self
+            .cursor_in(measure_num)
+            .filter(|cursor| cursor.playing)
+            .map(|cursor| cursor.beat);
+        self.lyrics
+            .iter()
+            .filter_map(|line| {
+                let syllables: Vec<(usize, String)> = line
+                    .iter()
+                    .filter(|syllable| syllable.measure == measure_num)
+                    .filter_map(|syllable| {
+                        let beat_num = beats
+                            .iter()
+                            .position(|beat| beat.start == Some(syllable.start))?;
+                        Some((beat_num, syllable.text.clone()))
+                    })
+                    .collect();
+                if syllables.is_empty() {
+                    return None;
+                }
+                let current = playing_beat.and_then(|playing_beat| {
+                    syllables
+                        .iter()
+                        .rposition(|(beat_num, _)| *beat_num <= playing_beat)
+                });
+                Some(LyricsText { syllables, current })
+            })
+            .collect()
+    }
+
+    /// Names of the chords stored on the beats, and of the chords found from the notes where
+    /// they change.
+    fn chord_names(&self, measure_num: usize) -> Option<ChordsText> {
+        if self.track.percussion_track {
+            return None;
+        }
+        let mut names = Vec::new();
+        let mut previous: Option<String> = None;
+        for (beat_num, beat) in self.measure_beats(measure_num).beats.iter().enumerate() {
+            let name = ChordShape::from_beat(beat, &self.track.strings).map(|shape| shape.name);
+            if let Some(name) = &name {
+                if beat.effect.chord.is_some() || previous.as_ref() != Some(name) {
+                    names.push((beat_num, name.clone()));
+                }
+            }
+            previous = name;
+        }
+        (!names.is_empty()).then_some(ChordsText { names })
+    }
+
+    /// Every chord of the track, once for each name.
+    pub fn chord_shapes(&self) -> Vec<ChordShape> {
+        let mut shapes: Vec<ChordShape> = Vec::new();
+        if self.track.percussion_track {
+            return shapes;
+        }
+        for measure_num in 0..self.track.measures.len() {
+            for beat in &self.measure_beats(measure_num).beats {
+                if let Some(shape) = ChordShape::from_beat(beat, &self.track.strings) {
+                    if !shapes.iter().any(|known| known.name == shape.name) {
+                        shapes.push(shape);
+                    }
+                }
+            }
+        }
+        shapes
+    }
+
+    /// Measure number followed by the rehearsal marker, if any. The number is a warning when
+    /// the beats do not fill the time signature.
+    fn measure_title(&self, measure_num: usize) -> Vec<Cell> {
+        let fill = self
+            .headers
+            .get(measure_num)
+            .map_or(Ordering::Equal, |header| {
+                voices::fill(&self.track.measures[measure_num], header)
+            });
+        let role = match fill {
+            Ordering::Equal => Role::Text,
+            _ => Role::Warning,
+        };
+        let mut title = cells(&(measure_num + 1).to_string(), role);
+        match fill {
+            Ordering::Greater => title.extend(cells(" (too long)", role)),
+            Ordering::Less => title.extend(cells(" (too short)", role)),
+            Ordering::Equal => (),
+        }
+        let marker = self
+            .headers
+            .get(measure_num)
+            .and_then(|header| header.marker.as_ref());
+        if let Some(marker) = marker {
+            title.push(Cell::new(" ", Role::Text));
+            title.extend(cells(&format!("[{}]", marker.title), Role::Marker));
+        }
+        title
+    }
+
+    /// Rows taken by `measure_num`, plus two rows of space after it.
+    pub fn measure_height(&self, measure_num: usize) -> usize {
+        let staff_height = self
+            .staff_text(measure_num)
+            .map_or(0, |staff| staff.height());
+        let lanes_height = self.lanes(measure_num).len();
+        let lyrics_height = self.lyrics_text(measure_num).len();
+        let chords_height = self.chord_names(measure_num).map_or(0, |_| 1);
+        1 + staff_height
+            + chords_height
+            + lanes_height
+            + self.track.strings.len()
+            + lyrics_height
+            + 2
+    }
+
+    /// The measures in `range` one under the other, as they are drawn on screen.
+    pub fn render(&self, range: Range<usize>) -> Grid {
+        let rows = self
+            .measures(range)
+            .into_iter()
+            .flat_map(|measure| measure.grid.rows)
+            .collect();
+        Grid { rows }
+    }
+
+    /// Lays out the measures in `range`. Ties and hammer-ons are drawn across the bar lines
+    /// between them, but not to measures outside the range.
+    pub fn measures(&self, range: Range<usize>) -> Vec<MeasureLayout> {
+        let mut measures: Vec<MeasureText> = Vec::new();
+        for measure_num in range.clone() {
+            let measure_beats = self.measure_beats(measure_num);
+            let measure = MeasureText {
+                title: self.measure_title(measure_num),
+                strings: vec![
+                    vec![NoteText::new(); measure_beats.beats.len()];
+                    self.track.strings.len()
+                ],
+                beats: measure_beats.beats,
+                staff: self.staff_text(measure_num),
+                chords: self.chord_names(measure_num),
+                lanes: self.lanes(measure_num),
+                lyrics: self.lyrics_text(measure_num),
+                voices: measure_beats.voices,
+                repeat_open: self
+                    .headers
+                    .get(measure_num)
+                    .is_some_and(|header| header.repeat_open),
+                repeat_close: self
+                    .headers
+                    .get(measure_num)
+                    .map_or(-1, |header| header.repeat_close),
+            };
+            measures.push(measure);
+        }
+        for measure_num in 0..measures.len() {
+            for beat_num in 0..measures[measure_num].beats.len() {
+                for note_num in 0..measures[measure_num].beats[beat_num].notes.len() {
+                    let note_str =
+                        measures[measure_num].beats[beat_num].notes[note_num].string as usize - 1;
+                    if measures[measure_num].beats[beat_num].notes[note_num].kind == NoteType::Tie {
+                        measures[measure_num].strings[note_str][beat_num].tie_left = true;
+                        match measures[measure_num].strings[note_str].get_mut(if beat_num == 0 {
+                            usize::MAX
+                        } else {
+                            beat_num - 1
+                        }) {
+                            Some(note_text) => note_text.tie_right = true,
+                            None => {
+                                if measure_num != 0 {
+                                    if let Some(m) = measures.get_mut(measure_num - 1) {
+                                        if let Some(note_text) =
+                                            m.strings[note_str].get_mut(m.beats.len() - 1)
+                                        {
+                                            note_text.tie_right = true;
+                                        }
+                                    }
+                                }
+                            }
+                        };
+                    }
+                    if measures[measure_num].beats[beat_num].notes[note_num]
+                        .effect
+                        .hammer
+                    {
+                        // Hammer-on goes up the neck, pull-off goes down
+                        let note = &measures[measure_num].beats[beat_num].notes[note_num];
+                        let absolute_measure = range.start + measure_num;
+                        let next_measure = (absolute_measure + 1 < self.track.measures.len())
+                            .then(|| self.measure_beats(absolute_measure + 1));
+                        let pull_off = measures[measure_num]
+                            .beats
+                            .get(beat_num + 1)
+                            .or_else(|| next_measure.as_ref().and_then(|m| m.beats.first()))
+                            .and_then(|beat| beat.notes.iter().find(|n| n.string == note.string))
+                            .is_some_and(|next| next.value < note.value);
+                        measures[measure_num].strings[note_str][beat_num].hammer_right = true;
+                        measures[measure_num].strings[note_str][beat_num].pull_off = pull_off;
+                        match measures[measure_num].strings[note_str].get_mut(beat_num + 1) {
+                            Some(note) => note.hammer_left = true,
+                            None => match measures.get_mut(measure_num + 1) {
+                                None => (),
+                                Some(m) => m.strings[note_str][0].hammer_left = true,
+                            },
+                        }
+                    }
+                    // Transfering data to NoteText
+                    {
+                        let note_value =
+                            measures[measure_num].beats[beat_num].notes[note_num].value;
+                        measures[measure_num].strings[note_str][beat_num].value = note_value;
+                    }
+                    {
+                        let note_slides = measures[measure_num].beats[beat_num].notes[note_num]
+                            .effect
+                            .slides
+                            .clone();
+                        measures[measure_num].strings[note_str][beat_num].slides = note_slides;
+                    }
+                    {
+                        let note_bend = measures[measure_num].beats[beat_num].notes[note_num]
+                            .effect
+                            .bend
+                            .clone();
+                        measures[measure_num].strings[note_str][beat_num].bend = note_bend;
+                    }
+                    {
+                        // NoteType does not implement Clone trait, so...
+                        match measures[measure_num].beats[beat_num].notes[note_num].kind {
+                            NoteType::Tie => {
+                                measures[measure_num].strings[note_str][beat_num].note_type =
+                                    NoteType::Tie
+                            }
+                            NoteType::Rest => {
+                                measures[measure_num].strings[note_str][beat_num].note_type =
+                                    NoteType::Rest
+                            }
+                            NoteType::Dead => {
+                                measures[measure_num].strings[note_str][beat_num].note_type =
+                                    NoteType::Dead
+                            }
+                            NoteType::Normal => {
+                                measures[measure_num].strings[note_str][beat_num].note_type =
+                                    NoteType::Normal
+                            }
+                            NoteType::Unknown(x) => {
+                                measures[measure_num].strings[note_str][beat_num].note_type =
+                                    NoteType::Unknown(x)
+                            }
+                        }
+                    }
+                    {
+                        let note_harmonic = measures[measure_num].beats[beat_num].notes[note_num]
+                            .effect
+                            .harmonic
+                            .clone();
+                        measures[measure_num].strings[note_str][beat_num].harmonic = note_harmonic;
+                    }
+                    {
+                        let note_grace = measures[measure_num].beats[beat_num].notes[note_num]
+                            .effect
+                            .grace
+                            .clone();
+                        measures[measure_num].strings[note_str][beat_num].grace = note_grace;
+                    }
+                    measures[measure_num].strings[note_str][beat_num].ghost_note =
+                        measures[measure_num].beats[beat_num].notes[note_num]
+                            .effect
+                            .ghost_note;
+                    measures[measure_num].strings[note_str][beat_num].is_highlighted =
+                        self.options.highlighted_voice
+                            == Some(measures[measure_num].voices[beat_num][note_num]);
+                }
+                if let Some(cursor) = self
+                    .cursor_in(range.start + measure_num)
+                    .filter(|cursor| cursor.beat == beat_num)
+                {
+                    // The whole beat in view mode, a single string in edit mode
+                    for string_num in 0..self.track.strings.len() {
+                        measures[measure_num].strings[string_num][beat_num].is_selected =
+                            !self.options.edit_mode || string_num == cursor.string;
+                    }
+                }
+            }
+        }
+        measures
+            .iter()
+            .map(|measure| measure.layout(self.options.unicode))
+            .collect()
+    }
+}
+
+#[derive(Clone)]
+struct NoteText {
+    pub note_type: NoteType,
+    pub value: i16,
+    pub is_selected: bool,
+    pub is_highlighted: bool,
+    pub tie_left: bool,
+    pub tie_right: bool,
+    pub hammer_left: bool,
+    pub hammer_right: bool,
+    pub pull_off: bool,
+    pub harmonic: Option<HarmonicEffect>,
+    pub ghost_note: bool,
+    pub slides: Vec<SlideType>,
+    pub bend: Option<BendEffect>,
+    pub grace: Option<GraceEffect>,
+    /// Draws the dot of a repeat sign on the string line
+    pub repeat_dot: bool,
+}
+
+struct MeasureText {
+    title: Vec<Cell>,
+    beats: Vec<Beat>,
+    staff: Option<StaffText>,
+    chords: Option<ChordsText>,
+    lanes: Vec<LaneText>,
+    lyrics: Vec<LyricsText>,
+    voices: Vec<Vec<usize>>,
+    repeat_open: bool,
+    repeat_close: i8,
+    strings: Vec<Vec<NoteText>>,
+}
+
+impl MeasureText {
+    pub fn layout(&self, unicode: bool) -> MeasureLayout {
+        let mut rows = vec![Vec::new(); self.strings.len()];
+        let strings_len = self.strings.len();
+        let last_string = strings_len.saturating_sub(1);
+        for (string_num, row) in rows.iter_mut().enumerate() {
+            let prefix = match string_num {
+                0 if self.repeat_open => '╓',
+                0 => '╭',
+                _ if string_num == last_string && self.repeat_open => '╙',
+                _ if string_num == last_string => '╰',
+                _ if self.repeat_open => '╟',
+                _ => '├',
+            };
+            row.push(Cell::new(prefix, Role::BarLine));
+            // Repeat dots go on the second and the second to last strings
+            let is_dot_row = string_num == 1 || string_num + 2 == strings_len;
+            for (beat_num, note) in self.strings[string_num].iter().enumerate() {
+                if beat_num == 0 && self.repeat_open && is_dot_row {
+                    let note = NoteText {
+                        repeat_dot: true,
+                        ..note.clone()
+                    };
+                    row.extend(note.cells(unicode));
+                } else {
+                    row.extend(note.cells(unicode));
+                }
+            }
+            if self.repeat_close > 0 {
+                let mut close = String::from(if is_dot_row { "•" } else { "─" });
+                match string_num {
+                    0 => close.push('╖'),
+                    _ if string_num == last_string => close.push('╜'),
+                    _ => close.push('╢'),
+                }
+                row.extend(cells(&close, Role::BarLine));
+                if string_num == 0 {
+                    row.extend(cells(&format!(" ×{}", self.repeat_close + 1), Role::Text));
+                }
+            }
+        }
+        for line in &self.lyrics {
+            rows.push(line.cells());
+        }
+        let mut top = vec![self.title.clone()];
+        if let Some(chords) = &self.chords {
+            top.push(chords.cells());
+        }
+        if let Some(staff) = &self.staff {
+            top.extend(staff.rows());
+        }
+        for lane in &self.lanes {
+            top.push(lane.cells());
+        }
+        let strings = top.len()..top.len() + strings_len;
+        top.extend(rows);
+        if !unicode {
+            for cell in top.iter_mut().flatten() {
+                cell.glyph = plain_text(&cell.glyph);
+            }
+        }
+        MeasureLayout {
+            grid: Grid { rows: top },
+            strings,
+            rhythm: self.beats.iter().map(Rhythm::new).collect(),
+        }
+    }
+}
+
+impl NoteText {
+    pub fn new() -> Self {
+        NoteText {
+            note_type: NoteType::Normal,
+            value: -1,
+            is_selected: false,
+            is_highlighted: false,
+            tie_left: false,
+            tie_right: false,
+            hammer_left: false,
+            hammer_right: false,
+            pull_off: false,
+            harmonic: None,
+            ghost_note: false,
+            slides: Vec::new(),
+            bend: None,
+            grace: None,
+            repeat_dot: false,
+        }
+    }
+
+    /// Six cells: a slide or grace note, a ghost note bracket or harmonic, up to two fret
+    /// digits and the bend, slide or hammer-on after them.
+    pub fn cells(&self, unicode: bool) -> Vec<Cell> {
+        let mut parts: Vec<String> = vec![String::from("─"); MeasureLayout::BEAT_WIDTH];
+        let mut is_value_of_len_two = false;
+        if self.value != -1 {
+            match self.note_type {
+                // Ties are drawn with combining arcs, which plain text has no room for
+                NoteType::Tie if !unicode => parts[2] = "~".to_string(),
+                NoteType::Tie => (),
+                NoteType::Rest => parts[2] = "P".to_string(),
+                NoteType::Dead => parts[2] = "X".to_string(),
+                NoteType::Normal => {
+                    is_value_of_len_two = self.value >= 10;
+                    if is_value_of_len_two {
+                        parts[2] = (self.value / 10).to_string();
+                        parts[3] = (self.value % 10).to_string();
+                    } else {
+                        parts[2] = self.value.to_string();
+                    }
+                }
+                NoteType::Unknown(_) => parts[2] = "u".to_string(),
+            }
+            if self.ghost_note {
+                parts[1] = "(".to_string();
+                if is_value_of_len_two {
+                    parts[4] = ")".to_string();
+                } else {
+                    parts[3] = ")".to_string();
+                }
+            }
+            for slide in &self.slides {
+                match slide {
+                    SlideType::None => (),
+                    SlideType::IntoFromAbove => parts[0] = "╲".to_string(),
+                    SlideType::IntoFromBelow => parts[0] = "╱".to_string(),
+                    SlideType::ShiftSlideTo => parts[5] = "╱".to_string(),
+                    SlideType::LegatoSlideTo => parts[5] = "╱".to_string(),
+                    SlideType::OutUpWards => parts[5] = "╱".to_string(),
+                    SlideType::OutDownwards => parts[5] = "╲".to_string(),
+                }
+            }
+            if let Some(bend) = &self.bend {
+                let mut start = if is_value_of_len_two { 4 } else { 3 };
+                if self.ghost_note {
+                    start += 1;
+                }
+                for (part, glyph) in parts
+                    .iter_mut()
+                    .skip(start)
+                    .zip(effects::bend_glyphs(bend).chars())
+                {
+                    *part = glyph.to_string();
+                }
+            }
+            if let Some(harmonic) = &self.harmonic {
+                if !self.ghost_note {
+                    parts[1] = effects::harmonic_glyph(harmonic).to_string();
+                }
+            }
+            if let Some(grace) = &self.grace {
+                let text = effects::grace_text(grace.fret, grace.is_dead);
+                let start = if text.chars().count() > 1 || parts[1] != "─" {
+                    0
+                } else {
+                    1
+                };
+                for (part, digit) in parts.iter_mut().skip(start).zip(text.chars()) {
+                    *part = digit.to_string();
+                }
+            }
+            if self.tie_left {
+                parts[0].push('︭');
+                parts[1].push_str("︨︬");
+            }
+            if self.tie_right {
+                parts[2].push_str("︧︫");
+                for part in parts.iter_mut().skip(3) {
+                    part.push('︭');
+                }
+            }
+            if self.hammer_left {
+                parts[0].push('︦');
+                parts[1].push_str("︡︥");
+            }
+            if self.hammer_right {
+                // A slide or bend after the fret keeps the last cell, the mark then goes in the
+                // cell before it when that one is free, else only the arc shows the hammer-on
+                let mark = if self.pull_off { "p" } else { "h" };
+                if let Some(part) = parts[4..]
+                    .iter_mut()
+                    .rev()
+                    .find(|part| part.starts_with('─'))
+                {
+                    part.replace_range(..'─'.len_utf8(), mark);
+                }
+                parts[2].push_str("︠︤");
+                for part in parts.iter_mut().skip(3) {
+                    part.push('︦');
+                }
+            }
+        }
+        if self.repeat_dot && parts[0].starts_with('─') {
+            parts[0].replace_range(..'─'.len_utf8(), "•");
+        }
+
+        let has_fret = self.value != -1 && self.note_type != NoteType::Tie;
+        let fret_end = if is_value_of_len_two { 3 } else { 2 };
+        parts
+            .into_iter()
+            .enumerate()
+            .map(|(part_num, part)| {
+                let is_fret = (2..=fret_end).contains(&part_num);
+                let role = if self.is_selected && is_fret {
+                    Role::Selection
+                } else if self.is_highlighted && is_fret {
+                    Role::Highlight
+                } else if is_fret && has_fret {
+                    Role::Fret
+                } else if part.starts_with('─') || part.starts_with('•') {
+                    Role::BarLine
+                } else {
+                    Role::Technique
+                };
+                Cell::new(part, role)
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,261 @@
+use guitarpro::beat::Beat;
+use guitarpro::enums::NoteType;
+
+use super::{Cell, MeasureLayout, Role};
+
+/// MIDI pitch of middle C.
+const MIDDLE_C: i8 = 60;
+
+// (letter, accidental) for every pitch class, letters counted from C
+const SHARP_SPELLING: [(i16, Accidental); 12] = [
+    (0, Accidental::Natural),
+    (0, Accidental::Sharp),
+    (1, Accidental::Natural),
+    (1, Accidental::Sharp),
+    (2, Accidental::Natural),
+    (3, Accidental::Natural),
+    (3, Accidental::Sharp),
+    (4, Accidental::Natural),
+    (4, Accidental::Sharp),
+    (5, Accidental::Natural),
+    (5, Accidental::Sharp),
+    (6, Accidental::Natural),
+];
+const FLAT_SPELLING: [(i16, Accidental); 12] = [
+    (0, Accidental::Natural),
+    (1, Accidental::Flat),
+    (1, Accidental::Natural),
+    (2, Accidental::Flat),
+    (2, Accidental::Natural),
+    (3, Accidental::Natural),
+    (4, Accidental::Flat),
+    (4, Accidental::Natural),
+    (5, Accidental::Flat),
+    (5, Accidental::Natural),
+    (6, Accidental::Flat),
+    (6, Accidental::Natural),
+];
+
+const LETTERS: [char; 7] = ['C', 'D', 'E', 'F', 'G', 'A', 'B'];
+// Tonic of every key signature, from 7 flats to 7 sharps
+const MAJOR_KEYS: [&str; 15] = [
+    "C♭", "G♭", "D♭", "A♭", "E♭", "B♭", "F", "C", "G", "D", "A", "E", "B", "F♯", "C♯",
+];
+const MINOR_KEYS: [&str; 15] = [
+    "A♭", "E♭", "B♭", "F", "C", "G", "D", "A", "E", "B", "F♯", "C♯", "G♯", "D♯", "A♯",
+];
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Accidental {
+    Natural,
+    Sharp,
+    Flat,
+}
+
+/// Clef of the staff. Both are written an octave above the sounding pitch, as for guitar and
+/// bass.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Clef {
+    Treble,
+    Bass,
+}
+
+#[derive(Clone)]
+pub struct StaffNote {
+    /// Diatonic step of the written pitch, `octave * 7 + letter`
+    pub step: i16,
+    pub accidental: Accidental,
+    pub dead: bool,
+}
+
+#[derive(Clone)]
+pub struct StaffColumn {
+    pub notes: Vec<StaffNote>,
+    pub hollow: bool,
+    pub is_selected: bool,
+}
+
+pub struct StaffText {
+    pub clef: Clef,
+    pub columns: Vec<StaffColumn>,
+}
+
+/// Name of a pitch class without the octave, e.g. "F♯".
+pub fn pitch_name(pitch: i16) -> String {
+    let (letter, accidental) = SHARP_SPELLING[pitch.rem_euclid(12) as usize];
+    let mut name = LETTERS[letter as usize].to_string();
+    if accidental == Accidental::Sharp {
+        name.push('♯');
+    }
+    name
+}
+
+/// Name of a key signature from its number of sharps, or of flats when negative.
+pub fn key_name(key: i8, is_minor: bool) -> String {
+    let index = (key.clamp(-7, 7) + 7) as usize;
+    if is_minor {
+        format!("{} minor", MINOR_KEYS[index])
+    } else {
+        format!("{} major", MAJOR_KEYS[index])
+    }
+}
+
+impl Clef {
+    /// Bass clef when no string of `tuning` is as high as middle C.
+    pub fn for_tuning(tuning: &[(i8, i8)]) -> Self {
+        if tuning.iter().all(|(_, pitch)| *pitch < MIDDLE_C) {
+            Clef::Bass
+        } else {
+            Clef::Treble
+        }
+    }
+
+    /// Diatonic steps of the bottom and the top line of the staff: E4 and F5 in treble clef,
+    /// G2 and A3 in bass clef.
+    fn lines(self) -> (i16, i16) {
+        match self {
+            Clef::Treble => (30, 38),
+            Clef::Bass => (18, 26),
+        }
+    }
+
+    /// Sign of the clef and the diatonic step of the line it marks, G4 or F3.
+    fn sign(self) -> (&'static str, i16) {
+        match self {
+            Clef::Treble => ("𝄞", 32),
+            Clef::Bass => ("𝄢", 24),
+        }
+    }
+}
+
+impl StaffNote {
+    /// Written an octave above the sounding `pitch`, as the clefs are 8va bassa.
+    pub fn new(pitch: i16, use_flats: bool, dead: bool) -> Self {
+        let written = pitch + 12;
+        let spelling = if use_flats {
+            FLAT_SPELLING
+        } else {
+            SHARP_SPELLING
+        };
+        let (letter, accidental) = spelling[written.rem_euclid(12) as usize];
+        StaffNote {
+            step: (written.div_euclid(12) - 1) * 7 + letter,
+            accidental,
+            dead,
+        }
+    }
+}
+
+impl StaffColumn {
+    /// `tuning` is the track's `strings` list, `use_flats` follows the key signature.
+    pub fn new(beat: &Beat, tuning: &[(i8, i8)], use_flats: bool) -> Self {
+        let mut notes = Vec::new();
+        for note in &beat.notes {
+            let dead = match note.kind {
+                NoteType::Normal | NoteType::Tie => false,
+                NoteType::Dead => true,
+                NoteType::Rest | NoteType::Unknown(_) => continue,
+            };
+            if note.string < 1 || note.string as usize > tuning.len() {
+                continue;
+            }
+            let pitch = tuning[note.string as usize - 1].1 as i16 + note.value;
+            notes.push(StaffNote::new(pitch, use_flats, dead));
+        }
+        StaffColumn {
+            notes,
+            hollow: beat.duration.value <= 2,
+            is_selected: false,
+        }
+    }
+
+    fn needs_ledger(&self, step: i16, clef: Clef) -> bool {
+        let (bottom, top) = clef.lines();
+        self.notes
+            .iter()
+            .any(|note| (step < bottom && note.step <= step) || (step > top && note.step >= step))
+    }
+
+    fn cells(&self, step: i16, clef: Clef, fill: &str) -> Vec<Cell> {
+        let mut parts: Vec<String> = vec![String::from(fill); MeasureLayout::BEAT_WIDTH];
+        let is_line = (step - clef.lines().0).rem_euclid(2) == 0;
+        if is_line && self.needs_ledger(step, clef) {
+            for part in parts.iter_mut().take(4).skip(1) {
+                *part = "─".to_string();
+            }
+        }
+        if let Some(note) = self.notes.iter().find(|note| note.step == step) {
+            parts[2] = if note.dead {
+                "x".to_string()
+            } else if self.hollow {
+                "○".to_string()
+            } else {
+                "●".to_string()
+            };
+            match note.accidental {
+                Accidental::Natural => (),
+                Accidental::Sharp => parts[1] = "♯".to_string(),
+                Accidental::Flat => parts[1] = "♭".to_string(),
+            }
+        }
+        let has_note = self.notes.iter().any(|note| note.step == step);
+        parts
+            .into_iter()
+            .enumerate()
+            .map(|(part_num, part)| {
+                let role = match part_num {
+                    2 if has_note && self.is_selected => Role::Selection,
+                    1 | 2 if has_note && part != fill && part != "─" => Role::Fret,
+                    _ if part == "─" => Role::BarLine,
+                    _ => Role::Text,
+                };
+                Cell::new(part, role)
+            })
+            .collect()
+    }
+}
+
+impl StaffText {
+    fn range(&self) -> (i16, i16) {
+        let steps = self
+            .columns
+            .iter()
+            .flat_map(|column| &column.notes)
+            .map(|note| note.step);
+        let (bottom, top) = self.clef.lines();
+        (
+            steps.clone().fold(bottom, i16::min),
+            steps.fold(top, i16::max),
+        )
+    }
+
+    pub fn height(&self) -> usize {
+        let (bottom, top) = self.range();
+        (top - bottom + 1) as usize
+    }
+
+    pub fn rows(&self) -> Vec<Vec<Cell>> {
+        let (bottom, top) = self.range();
+        let (staff_bottom, staff_top) = self.clef.lines();
+        let mut result = Vec::new();
+        for step in (bottom..=top).rev() {
+            let in_staff = (staff_bottom..=staff_top).contains(&step);
+            let is_line = (step - staff_bottom).rem_euclid(2) == 0;
+            let fill = if in_staff && is_line { "─" } else { " " };
+            // The clef is drawn over the start of the line it marks
+            let (sign, sign_step) = self.clef.sign();
+            let mut line = vec![if step == sign_step {
+                Cell::new(sign, Role::BarLine)
+            } else if in_staff {
+                Cell::new("│", Role::BarLine)
+            } else {
+                Cell::new(" ", Role::Text)
+            }];
+            for column in &self.columns {
+                line.extend(column.cells(step, self.clef, fill));
+            }
+            result.push(line);
+        }
+        result
+    }
+}
@@ -0,0 +1,106 @@
+use std::time::Duration;
+
+use guitarpro::beat::Beat;
+use guitarpro::headers::MeasureHeader;
+use guitarpro::key_signature::DURATION_QUARTER_TIME;
+
+use super::voices::{beat_ticks, measure_ticks};
+
+pub struct Step {
+    /// Position in the roadmap
+    pub roadmap_pos: usize,
+    pub measure: usize,
+    pub beat: usize,
+    pub duration: Duration,
+}
+
+/// Beats of the track in play order, with the time each one lasts.
+pub struct Sequencer {
+    steps: Vec<Step>,
+    position: usize,
+}
+
+impl Sequencer {
+    /// `beats[measure]` are the beats drawn for that measure, starting from the measure start,
+    /// `roadmap` is the play order of the measures and `tempo` the song tempo in quarter notes
+    /// per minute.
+    pub fn new(
+        beats: &[Vec<Beat>],
+        headers: &[MeasureHeader],
+        roadmap: &[usize],
+        tempo: i16,
+    ) -> Self {
+        let mut steps = Vec::new();
+        let mut tempo = tempo.max(1) as f64;
+        for (roadmap_pos, &measure) in roadmap.iter().enumerate() {
+            let measure_beats = &beats[measure];
+            let measure_end = headers.get(measure).map(measure_ticks);
+            for (beat_num, beat) in measure_beats.iter().enumerate() {
+                if let Some(item) = beat
+                    .effect
+                    .mix_table_change
+                    .as_ref()
+                    .and_then(|mix| mix.tempo.as_ref())
+                {
+                    tempo = item.value.max(1) as f64;
+                }
+                let ticks = match (beat.start, measure_beats.get(beat_num + 1)) {
+                    (
+                        Some(start),
+                        Some(Beat {
+                            start: Some(next), ..
+                        }),
+                    ) => next - start,
+                    (Some(start), None) => measure_end.map_or(0, |end| end - start),
+                    _ => 0,
+                };
+                let ticks = if ticks > 0 { ticks } else { beat_ticks(beat) };
+                steps.push(Step {
+                    roadmap_pos,
+                    measure,
+                    beat: beat_num,
+                    duration: Duration::from_secs_f64(
+                        ticks as f64 / DURATION_QUARTER_TIME as f64 * 60.0 / tempo,
+                    ),
+                });
+            }
+        }
+        Sequencer { steps, position: 0 }
+    }
+
+    /// Moves to `beat` of the measure at `roadmap_pos`.
+    pub fn seek(&mut self, roadmap_pos: usize, beat: usize) {
+        self.position = self
+            .steps
+            .iter()
+            .position(|step| step.roadmap_pos == roadmap_pos && step.beat >= beat)
+            .unwrap_or(0);
+    }
+
+    /// Time from the start of the song to `beat` of the measure at `roadmap_pos`.
+    pub fn time_at(&self, roadmap_pos: usize, beat: usize) -> Duration {
+        self.steps
+            .iter()
+            .take_while(|step| (step.roadmap_pos, step.beat) < (roadmap_pos, beat))
+            .map(|step| step.duration)
+            .sum()
+    }
+
+    /// Every beat in play order
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    pub fn total_time(&self) -> Duration {
+        self.steps.iter().map(|step| step.duration).sum()
+    }
+
+    pub fn current(&self) -> Option<&Step> {
+        self.steps.get(self.position)
+    }
+
+    pub fn advance(&mut self) -> Option<&Step> {
+        self.position += 1;
+        self.current()
+    }
+}
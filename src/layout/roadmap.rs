@@ -0,0 +1,60 @@
+use guitarpro::headers::MeasureHeader;
+
+/// Measure indexes in the order they are played, with repeats and alternate endings unrolled.
+pub fn roadmap(headers: &[MeasureHeader]) -> Vec<usize> {
+    let mut order = Vec::new();
+    let mut repeat_start = 0;
+    // Pass through the current repeated section, starting from 1
+    let mut pass: u32 = 1;
+    let mut jumped = false;
+    let mut measure = 0;
+    while measure < headers.len() {
+        let header = &headers[measure];
+        if header.repeat_open && !jumped {
+            repeat_start = measure;
+            pass = 1;
+        }
+        jumped = false;
+        if !is_played_on_pass(header, pass) {
+            measure += 1;
+            continue;
+        }
+        order.push(measure);
+        if header.repeat_close > 0 {
+            if pass <= header.repeat_close as u32 {
+                pass += 1;
+                measure = repeat_start;
+                jumped = true;
+                continue;
+            }
+            repeat_start = measure + 1;
+            pass = 1;
+        }
+        // After the last alternate ending the section is done, even when its closing measure
+        // was a skipped ending
+        let ends_endings = header.repeat_alternative != 0
+            && headers
+                .get(measure + 1)
+                .is_none_or(|next| next.repeat_alternative == 0);
+        if ends_endings {
+            repeat_start = measure + 1;
+            pass = 1;
+        }
+        measure += 1;
+    }
+    order
+}
+
+/// Alternate endings are stored as a bit mask, bit 0 being the 1st ending.
+fn is_played_on_pass(header: &MeasureHeader, pass: u32) -> bool {
+    header.repeat_alternative == 0
+        || (pass <= 8 && header.repeat_alternative & (1 << (pass - 1)) != 0)
+}
+
+/// Label of an alternate ending bracket, e.g. "1.2."
+pub fn alternative_label(repeat_alternative: u8) -> String {
+    (0..8)
+        .filter(|bit| repeat_alternative & (1 << bit) != 0)
+        .map(|bit| format!("{}.", bit + 1))
+        .collect()
+}
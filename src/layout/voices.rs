@@ -0,0 +1,196 @@
+use std::cmp::Ordering;
+
+use guitarpro::beat::Beat;
+use guitarpro::enums::BeatStatus;
+use guitarpro::headers::MeasureHeader;
+use guitarpro::key_signature::DURATION_QUARTER_TIME;
+use guitarpro::measure::Measure;
+
+/// Beats of several voices merged by their start in the measure. The `start` of every beat is
+/// its offset in ticks from the start of the measure.
+pub struct MeasureBeats {
+    pub beats: Vec<Beat>,
+    /// Voice of every note, `voices[beat][note]`
+    pub voices: Vec<Vec<usize>>,
+}
+
+impl MeasureBeats {
+    /// Voices without any note only add rests, so they are left out. When no visible voice has
+    /// notes the first visible one is used to keep the measure rhythm.
+    pub fn new(measure: &Measure, visible: &[bool]) -> Self {
+        let is_visible = |voice: &usize| visible.get(*voice).copied().unwrap_or(true);
+        let mut used: Vec<usize> = (0..measure.voices.len())
+            .filter(is_visible)
+            .filter(|voice| {
+                measure.voices[*voice]
+                    .beats
+                    .iter()
+                    .any(|beat| !beat.notes.is_empty())
+            })
+            .collect();
+        if used.is_empty() {
+            used.extend((0..measure.voices.len()).find(is_visible));
+        }
+
+        let mut merged: Vec<(Beat, Vec<usize>)> = Vec::new();
+        for voice in used {
+            for beat in with_offsets(&measure.voices[voice].beats) {
+                match merged.iter_mut().find(|(b, _)| b.start == beat.start) {
+                    Some((merged_beat, note_voices)) => {
+                        for note in &beat.notes {
+                            if !merged_beat.notes.iter().any(|n| n.string == note.string) {
+                                merged_beat.notes.push(note.clone());
+                                note_voices.push(voice);
+                            }
+                        }
+                    }
+                    None => {
+                        let note_voices = vec![voice; beat.notes.len()];
+                        merged.push((beat, note_voices));
+                    }
+                }
+            }
+        }
+        if merged.is_empty() {
+            if let Some(voice) = measure.voices.first() {
+                merged = with_offsets(&voice.beats)
+                    .into_iter()
+                    .map(|beat| {
+                        let note_voices = vec![0; beat.notes.len()];
+                        (beat, note_voices)
+                    })
+                    .collect();
+            }
+        }
+        merged.sort_by_key(|(beat, _)| beat.start);
+
+        let (beats, voices) = merged.into_iter().unzip();
+        MeasureBeats { beats, voices }
+    }
+}
+
+/// Copies of `beats` with `start` set from the written durations. The positions stored in the
+/// file are not reliable enough to line voices up or time them.
+pub fn with_offsets(beats: &[Beat]) -> Vec<Beat> {
+    let mut offset = 0;
+    beats
+        .iter()
+        .map(|beat| {
+            let mut beat = beat.clone();
+            beat.start = Some(offset);
+            offset += beat_ticks(&beat);
+            beat
+        })
+        .collect()
+}
+
+/// Index of the beat of `voice` that starts `start` ticks into the measure, to find the beat a
+/// merged one was copied from.
+pub fn beat_at(measure: &Measure, voice: usize, start: i64) -> Option<usize> {
+    with_offsets(&measure.voices.get(voice)?.beats)
+        .iter()
+        .position(|beat| beat.start == Some(start))
+}
+
+/// Length of a beat from its written duration.
+pub fn beat_ticks(beat: &Beat) -> i64 {
+    let duration = &beat.duration;
+    let mut ticks = DURATION_QUARTER_TIME * 4 / duration.value.max(1) as i64;
+    if duration.double_dotted {
+        ticks = ticks * 7 / 4;
+    } else if duration.dotted {
+        ticks = ticks * 3 / 2;
+    }
+    ticks * duration.tuplet_times.max(1) as i64 / duration.tuplet_enters.max(1) as i64
+}
+
+/// Length of a measure from its time signature.
+pub fn measure_ticks(header: &MeasureHeader) -> i64 {
+    let time = &header.time_signature;
+    let unit_ticks = DURATION_QUARTER_TIME * 4 / time.denominator.value.max(1) as i64;
+    time.numerator as i64 * unit_ticks
+}
+
+/// How the voices fill the time signature: `Greater` when one of them goes past it, `Less`
+/// when one falls short of it. Voices left empty in the file are not counted.
+pub fn fill(measure: &Measure, header: &MeasureHeader) -> Ordering {
+    let length = measure_ticks(header);
+    let voice_fills: Vec<Ordering> = measure
+        .voices
+        .iter()
+        .filter(|voice| {
+            voice
+                .beats
+                .iter()
+                .any(|beat| beat.status != BeatStatus::Empty)
+        })
+        .map(|voice| voice.beats.iter().map(beat_ticks).sum::<i64>().cmp(&length))
+        .collect();
+    if voice_fills.contains(&Ordering::Greater) {
+        Ordering::Greater
+    } else if voice_fills.contains(&Ordering::Less) {
+        Ordering::Less
+    } else {
+        Ordering::Equal
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use guitarpro::beat::Voice;
+    use guitarpro::enums::NoteType;
+    use guitarpro::note::Note;
+
+    use super::*;
+
+    /// Voice with a note on `string` for every duration value in `durations`.
+    pub(crate) fn voice(string: i8, durations: &[u16]) -> Voice {
+        let beats = durations
+            .iter()
+            .map(|value| {
+                let mut beat = Beat::default();
+                beat.duration.value = *value;
+                let mut note = Note::default();
+                note.string = string;
+                note.kind = NoteType::Normal;
+                beat.notes.push(note);
+                beat
+            })
+            .collect();
+        Voice {
+            beats,
+            ..Voice::default()
+        }
+    }
+
+    #[test]
+    fn voices_merge_by_offset() {
+        let measure = Measure {
+            voices: vec![voice(1, &[2, 2]), voice(6, &[4, 4, 4, 4])],
+            ..Measure::default()
+        };
+        let quarter = DURATION_QUARTER_TIME;
+        let starts =
+            |beats: &[Beat]| -> Vec<i64> { beats.iter().map(|beat| beat.start.unwrap()).collect() };
+        assert_eq!(
+            starts(&with_offsets(&measure.voices[0].beats)),
+            [0, 2 * quarter]
+        );
+
+        let merged = MeasureBeats::new(&measure, &[true, true]);
+        assert_eq!(
+            starts(&merged.beats),
+            [0, quarter, 2 * quarter, 3 * quarter]
+        );
+        assert_eq!(merged.voices, [vec![0, 1], vec![1], vec![0, 1], vec![1]]);
+        let strings: Vec<i8> = merged.beats[2].notes.iter().map(|n| n.string).collect();
+        assert_eq!(strings, [1, 6]);
+
+        let hidden = MeasureBeats::new(&measure, &[false, true]);
+        assert_eq!(
+            starts(&hidden.beats),
+            [0, quarter, 2 * quarter, 3 * quarter]
+        );
+        assert_eq!(hidden.voices, [vec![1], vec![1], vec![1], vec![1]]);
+    }
+}
@@ -1,15 +1,38 @@
-mod tabsprint;
+mod args;
+mod bindings;
+mod config;
 
+use args::Args;
+use bindings::{Action, Bindings, Lookup};
 use guitarpro::*;
+use rstabs::export::{self, pdf, Page};
+use rstabs::layout::{LayoutOptions, TabLayout};
+use rstabs::tabsprint;
+use std::env;
 use std::fs;
 use std::io::Read;
+use std::path::Path;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::{Duration, Instant};
+use tabsprint::BeatEdit;
+use tabsprint::Direction;
+use tabsprint::Effect;
+use tabsprint::Input;
 use tabsprint::ReadInput;
 use tabsprint::TabsPrint;
+use tabsprint::Theme;
+
+/// How often the terminal size is checked while waiting for input
+const RESIZE_CHECK: Duration = Duration::from_millis(100);
 
 fn main() {
+    let args = Args::parse(env::args().skip(1)).unwrap_or_else(|err| {
+        eprintln!("{}", err);
+        std::process::exit(2);
+    });
     let mut f = fs::OpenOptions::new()
         .read(true)
-        .open("resources/I Built The Sky-Up Into the Ether.gp5")
+        .open(&args.song)
         .unwrap_or_else(|_error| {
             panic!("Unknown error while reading file");
         });
@@ -20,28 +43,302 @@ fn main() {
     let mut song: gp::Song = gp::Song::default();
     song.read_gp5(&data);
 
+    if args.is_export() {
+        if let Err(err) = export_song(&song, &args) {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let mut terminal = tabsprint::Terminal::new();
-    terminal.set_tab(song.tracks);
+    terminal.set_song(song);
 
+    // Config errors are shown on the command line until the first key
+    let mut show_error = false;
+    let settings =
+        config::read().and_then(|config| Ok((Bindings::new(&config)?, load_theme(&config)?)));
+    let bindings = match settings {
+        Ok((bindings, theme)) => {
+            terminal.set_theme(theme);
+            bindings
+        }
+        Err(err) => {
+            terminal.set_command_line(Some(err));
+            show_error = true;
+            Bindings::default()
+        }
+    };
+
+    let input = terminal.read_input();
+    // End of the beat being played, `None` when not playing
+    let mut next_step: Option<Instant> = None;
+    // Command typed after ':', e.g. ":12" to go to measure 12
+    let mut command: Option<String> = None;
+    // Keys typed so far of a sequence like "gg"
+    let mut pending: Vec<console::Key> = Vec::new();
+    let mut show_help = false;
     loop {
-        let key = terminal.read_key();
-        match key {
-            Ok(console::Key::Char('h')) | Ok(console::Key::ArrowLeft) => {
-                terminal.cursor_move(tabsprint::Direction::Left)
+        let wait = next_step.map_or(RESIZE_CHECK, |step| {
+            step.saturating_duration_since(Instant::now())
+                .min(RESIZE_CHECK)
+        });
+        let input = match input.recv_timeout(wait) {
+            Ok(input) => input,
+            Err(RecvTimeoutError::Timeout) => {
+                if next_step.is_some_and(|step| step <= Instant::now()) {
+                    next_step = terminal.play_step().map(|wait| Instant::now() + wait);
+                }
+                terminal.check_size();
+                continue;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        };
+        let key = match input {
+            Ok(Input::Key(key)) => key,
+            Ok(Input::Mouse(mouse)) => {
+                terminal.mouse(mouse);
+                continue;
+            }
+            Err(err) => {
+                eprintln!("{}", err);
+                continue;
+            }
+        };
+        if show_error {
+            show_error = false;
+            terminal.set_command_line(None);
+        }
+        if let Some(text) = command.as_mut() {
+            match key {
+                console::Key::Char(ch) if ch.is_ascii_digit() => text.push(ch),
+                console::Key::Backspace if text.len() > 1 => {
+                    text.pop();
+                }
+                console::Key::Enter => {
+                    if let Ok(measure_num) = text[1..].parse() {
+                        terminal.goto_measure(measure_num);
+                    }
+                    command = None;
+                }
+                _ => command = None,
+            }
+            terminal.set_command_line(command.clone());
+            continue;
+        }
+
+        pending.push(key);
+        let action = match bindings.lookup(&pending) {
+            Lookup::Action(action) => action,
+            Lookup::Pending => continue,
+            Lookup::None => {
+                // Start over from the last key, which may begin another sequence
+                let is_sequence = pending.len() > 1;
+                let key = pending.pop();
+                pending.clear();
+                if !is_sequence {
+                    continue;
+                }
+                pending.extend(key);
+                match bindings.lookup(&pending) {
+                    Lookup::Action(action) => action,
+                    Lookup::Pending => continue,
+                    Lookup::None => {
+                        pending.clear();
+                        continue;
+                    }
+                }
+            }
+        };
+        pending.clear();
+        match action {
+            Action::MoveLeft => terminal.cursor_move(Direction::Left),
+            Action::MoveRight => terminal.cursor_move(Direction::Right),
+            Action::MoveUp => terminal.cursor_move(Direction::Up),
+            Action::MoveDown => terminal.cursor_move(Direction::Down),
+            Action::NextNote => terminal.cursor_move(Direction::NextNote),
+            Action::PreviousNote => terminal.cursor_move(Direction::PreviousNote),
+            Action::Start => terminal.cursor_move(Direction::Start),
+            Action::End => terminal.cursor_move(Direction::End),
+            Action::HalfPageUp => terminal.cursor_move(Direction::HalfPageUp),
+            Action::HalfPageDown => terminal.cursor_move(Direction::HalfPageDown),
+            Action::PageUp => terminal.cursor_move(Direction::PageUp),
+            Action::PageDown => terminal.cursor_move(Direction::PageDown),
+            Action::GoToMeasure => {
+                command = Some(String::from(":"));
+                terminal.set_command_line(command.clone());
             }
-            Ok(console::Key::Char('l')) | Ok(console::Key::ArrowRight) => {
-                terminal.cursor_move(tabsprint::Direction::Right)
+            Action::Markers => terminal.toggle_marker_menu(),
+            Action::ToggleNotation => terminal.toggle_notation(),
+            Action::CycleTheme => terminal.cycle_theme(),
+            Action::ChordPanel => terminal.toggle_chord_panel(),
+            Action::ToggleVoice1 => terminal.toggle_voice(0),
+            Action::ToggleVoice2 => terminal.toggle_voice(1),
+            Action::HighlightVoice => terminal.cycle_voice_highlight(),
+            Action::ToggleLinear => terminal.toggle_linear(),
+            Action::Play => next_step = terminal.toggle_play().map(|wait| Instant::now() + wait),
+            Action::Loop => terminal.toggle_loop(),
+            Action::ToggleEdit => terminal.toggle_edit(),
+            Action::ToggleHammer => terminal.toggle_effect(Effect::Hammer),
+            Action::ToggleSlideIn => terminal.toggle_effect(Effect::SlideIn),
+            Action::ToggleSlideOut => terminal.toggle_effect(Effect::SlideOut),
+            Action::ToggleTie => terminal.toggle_effect(Effect::Tie),
+            Action::ToggleGhostNote => terminal.toggle_effect(Effect::GhostNote),
+            Action::ToggleHarmonic => terminal.toggle_effect(Effect::Harmonic),
+            Action::ToggleDeadNote => terminal.toggle_effect(Effect::DeadNote),
+            Action::TogglePalmMute => terminal.toggle_effect(Effect::PalmMute),
+            Action::ToggleVibrato => terminal.toggle_effect(Effect::Vibrato),
+            Action::ToggleBend => terminal.toggle_effect(Effect::Bend),
+            Action::Shorter => terminal.edit_beat(BeatEdit::Shorter),
+            Action::Longer => terminal.edit_beat(BeatEdit::Longer),
+            Action::Dot => terminal.edit_beat(BeatEdit::Dot),
+            Action::Tuplet => terminal.edit_beat(BeatEdit::Tuplet),
+            Action::Rest => terminal.edit_beat(BeatEdit::Rest),
+            Action::NextTrack => {
+                terminal.cycle_track(true);
+                next_step = None;
             }
-            Ok(console::Key::Char('j')) | Ok(console::Key::ArrowUp) => {
-                terminal.cursor_move(tabsprint::Direction::Up)
+            Action::PreviousTrack => {
+                terminal.cycle_track(false);
+                next_step = None;
             }
-            Ok(console::Key::Char('k')) | Ok(console::Key::ArrowDown) => {
-                terminal.cursor_move(tabsprint::Direction::Down)
+            Action::TrackMenu => terminal.toggle_track_menu(),
+            Action::Help => {
+                show_help = !show_help;
+                terminal.set_help(show_help.then(|| bindings.help()));
             }
-            Ok(console::Key::Char('q')) => break,
-            Ok(_) => (),
-            Err(err) => eprintln!("{}", err),
+            Action::Cancel => {
+                if show_help {
+                    show_help = false;
+                    terminal.set_help(None);
+                }
+                terminal.menu_cancel();
+            }
+            Action::Confirm => terminal.menu_confirm(),
+            Action::Quit => break,
         }
     }
     // println!("{:?}", terminal.tab[0].measures[2].voices[0].beats[1]);
 }
+
+/// Theme named in the `[theme]` table of the config, e.g. `name = "dark"`, with the styles of
+/// the parts it lists replaced, e.g. `fret = "yellow.bold"`.
+fn load_theme(config: &[config::Entry]) -> Result<Theme, String> {
+    let mut theme = Theme::default();
+    let entries: Vec<&config::Entry> = config
+        .iter()
+        .filter(|entry| entry.table == "theme")
+        .collect();
+    // The name goes first so the other entries change the named theme
+    let (names, parts): (Vec<&config::Entry>, Vec<&config::Entry>) =
+        entries.into_iter().partition(|entry| entry.key == "name");
+    for entry in names.into_iter().chain(parts) {
+        let [value] = entry.values.as_slice() else {
+            return Err(format!("line {}: expected a single value", entry.line_num));
+        };
+        if entry.key == "name" {
+            theme = Theme::by_name(value)
+                .ok_or_else(|| format!("line {}: unknown theme {}", entry.line_num, value))?;
+        } else {
+            theme
+                .set(&entry.key, value)
+                .map_err(|err| format!("line {}: {}", entry.line_num, err))?;
+        }
+    }
+    Ok(theme)
+}
+
+/// Writes the tracks chosen in `args` to the files of the export options.
+fn export_song(song: &gp::Song, args: &Args) -> Result<(), String> {
+    if let Some(track_num) = args.tracks.iter().find(|num| **num >= song.tracks.len()) {
+        return Err(format!("no track {}", track_num + 1));
+    }
+    let page = Page {
+        systems: args.staves,
+        ..Page::default()
+    };
+    let write = |path: &Path, data: &[u8]| {
+        fs::write(path, data).map_err(|err| format!("{}: {}", path.display(), err))
+    };
+    if let Some(path) = &args.svg {
+        let track = &song.tracks[args.tracks.first().copied().unwrap_or(0)];
+        let pages = export::pages(&print_layout(song, track), 0..track.measures.len(), &page);
+        for (page_num, shapes) in pages.iter().enumerate() {
+            let page_path = export::page_path(path, page_num, pages.len());
+            write(&page_path, export::svg::document(&page, shapes).as_bytes())?;
+        }
+    }
+    if let Some(path) = &args.html {
+        let track = &song.tracks[args.tracks.first().copied().unwrap_or(0)];
+        let title = format!("{} - {}", song.name, track.name);
+        let html = export::html::document(&title, &print_layout(song, track), song.tempo, &page);
+        write(path, html.as_bytes())?;
+    }
+    // Every track in the formats with more than one
+    let track_nums: Vec<usize> = if args.tracks.is_empty() {
+        (0..song.tracks.len()).collect()
+    } else {
+        args.tracks.clone()
+    };
+    if let Some(path) = &args.pdf {
+        let tracks: Vec<pdf::TrackPages> = track_nums
+            .iter()
+            .enumerate()
+            .map(|(num, track_num)| {
+                let track = &song.tracks[*track_num];
+                // The title block goes on the first page, the track name on the first page of
+                // every track
+                let title = if num == 0 { pdf::TITLE_HEIGHT } else { 0.0 };
+                let page = Page {
+                    header: pdf::HEADER_HEIGHT,
+                    title: title + pdf::TRACK_HEIGHT,
+                    ..page
+                };
+                pdf::TrackPages {
+                    name: track.name.clone(),
+                    pages: export::pages(
+                        &print_layout(song, track),
+                        0..track.measures.len(),
+                        &page,
+                    ),
+                }
+            })
+            .collect();
+        let author = if song.writer.is_empty() {
+            &song.transcriber
+        } else {
+            &song.writer
+        };
+        let mut subtitle: Vec<String> = [&song.artist, &song.album]
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .cloned()
+            .collect();
+        if !author.is_empty() {
+            subtitle.push(format!("tab by {}", author));
+        }
+        let titles = pdf::Titles {
+            title: song.name.clone(),
+            subtitle: subtitle.join(" · "),
+        };
+        write(path, &pdf::document(&titles, &tracks, &page))?;
+    }
+    if let Some(path) = &args.ly {
+        write(
+            path,
+            export::lilypond::document(song, &track_nums).as_bytes(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Printed tab has the rhythm under the strings instead of a staff.
+fn print_layout<'a>(song: &'a gp::Song, track: &'a track::Track) -> TabLayout<'a> {
+    TabLayout {
+        options: LayoutOptions {
+            show_notation: false,
+            ..LayoutOptions::default()
+        },
+        ..TabLayout::new(track, &song.measure_headers)
+    }
+}
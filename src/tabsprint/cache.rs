@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use std::ops::Range;
+
+use super::theme::Theme;
+use crate::layout::playback::Sequencer;
+use crate::layout::LayoutOptions;
+
+/// Settings that change how every measure is drawn.
+#[derive(Clone, PartialEq)]
+pub struct DrawSettings {
+    pub track: usize,
+    pub options: LayoutOptions,
+    pub theme: Theme,
+}
+
+/// What the text of a measure depends on besides the tab and the settings.
+#[derive(Clone, Copy, PartialEq)]
+pub struct MeasureKey {
+    /// Beat and string of the cursor when it is in the measure, and whether it is playing
+    pub cursor: Option<(u16, u8, bool)>,
+    /// Ties and hammer-ons are drawn across a bar line only when both measures are on screen
+    pub first_on_screen: bool,
+    pub last_on_screen: bool,
+}
+
+/// Lines of a drawn measure.
+#[derive(Clone)]
+pub struct MeasureLines {
+    pub lines: Vec<String>,
+    /// Lines of the strings, from the first string
+    pub strings: Range<usize>,
+}
+
+/// Measures drawn before, and the lines on the screen, so a redraw only writes what changed.
+/// Also keeps what is found from the whole track, so moving the cursor does not go over it.
+#[derive(Default)]
+pub struct RenderCache {
+    settings: Option<DrawSettings>,
+    measures: HashMap<usize, (MeasureKey, MeasureLines)>,
+    /// Rows of every measure laid out, they do not depend on the cursor
+    heights: HashMap<usize, usize>,
+    /// Time of every beat of the track, for the footer
+    timeline: Option<Sequencer>,
+    screen: Vec<String>,
+    /// Size of the terminal the screen was written for
+    size: (u16, u16),
+}
+
+impl RenderCache {
+    /// Forgets the drawn measures, their heights and the timeline if the settings changed.
+    pub fn check_settings(&mut self, settings: DrawSettings) {
+        if self.settings.as_ref() != Some(&settings) {
+            self.measures.clear();
+            self.heights.clear();
+            self.timeline = None;
+            self.settings = Some(settings);
+        }
+    }
+
+    pub fn get(&self, measure_num: usize, key: &MeasureKey) -> Option<&MeasureLines> {
+        self.measures
+            .get(&measure_num)
+            .filter(|(drawn_key, _)| drawn_key == key)
+            .map(|(_, lines)| lines)
+    }
+
+    /// Forgets the drawn measures in `range` and their heights after their notes changed, and
+    /// the timeline.
+    pub fn forget(&mut self, range: Range<usize>) {
+        self.measures
+            .retain(|measure_num, _| !range.contains(measure_num));
+        self.heights
+            .retain(|measure_num, _| !range.contains(measure_num));
+        self.timeline = None;
+    }
+
+    pub fn insert(&mut self, measure_num: usize, key: MeasureKey, lines: MeasureLines) {
+        self.measures.insert(measure_num, (key, lines));
+    }
+
+    pub fn height(&self, measure_num: usize) -> Option<usize> {
+        self.heights.get(&measure_num).copied()
+    }
+
+    pub fn insert_height(&mut self, measure_num: usize, height: usize) {
+        self.heights.insert(measure_num, height);
+    }
+
+    pub fn timeline(&self) -> Option<&Sequencer> {
+        self.timeline.as_ref()
+    }
+
+    pub fn insert_timeline(&mut self, timeline: Sequencer) {
+        self.timeline = Some(timeline);
+    }
+
+    /// Lines on the terminal.
+    pub fn screen(&self) -> &[String] {
+        &self.screen
+    }
+
+    /// Rows of `screen` that differ from what is on the terminal with their lines, every row
+    /// if its size changed. `screen` becomes what is on the terminal.
+    pub fn changed_rows(&mut self, screen: Vec<String>, size: (u16, u16)) -> Vec<(usize, &str)> {
+        let redraw_all = size != self.size || screen.len() != self.screen.len();
+        let rows: Vec<usize> = (0..screen.len())
+            .filter(|row| redraw_all || screen[*row] != self.screen[*row])
+            .collect();
+        self.screen = screen;
+        self.size = size;
+        rows.into_iter()
+            .map(|row| (row, self.screen[row].as_str()))
+            .collect()
+    }
+}
@@ -1,444 +1,1557 @@
-use std::{u16, usize};
+mod cache;
+mod theme;
 
-use console::{style, Key, Term};
-use guitarpro::enums::{NoteType, SlideType};
+use std::fs;
+use std::ops::Range;
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::Duration;
+
+use cache::{DrawSettings, MeasureKey, MeasureLines, RenderCache};
+use console::{Key, Term};
+use guitarpro::beat::Beat;
+use guitarpro::effects::{BendEffect, BendPoint, HarmonicEffect};
+use guitarpro::enums::{BeatStatus, BendType, NoteType, SlideType};
+use guitarpro::gp::Song;
+use guitarpro::headers::MeasureHeader;
+use guitarpro::note::Note;
 use guitarpro::track::Track;
+pub use theme::Theme;
+
+use crate::layout::lyrics::{self, Syllable};
+use crate::layout::playback::Sequencer;
+use crate::layout::voices::{self, MeasureBeats};
+use crate::layout::{chords, notation, roadmap, Cursor, LayoutOptions, MeasureLayout, TabLayout};
+
+/// Effects of the note under the cursor that edit mode turns on and off
+#[derive(Clone, Copy)]
+pub enum Effect {
+    /// Hammer-on or pull-off to the next note, depending on which is higher
+    Hammer,
+    /// From below, from above, then none
+    SlideIn,
+    /// Slide to the next note, slide down out of the note, then none
+    SlideOut,
+    Tie,
+    GhostNote,
+    /// Natural harmonic
+    Harmonic,
+    DeadNote,
+    PalmMute,
+    Vibrato,
+    /// Full bend
+    Bend,
+}
+
+/// Changes to the duration of the beat under the cursor in edit mode
+#[derive(Clone, Copy)]
+pub enum BeatEdit {
+    /// Halves the note value, down to a sixty-fourth
+    Shorter,
+    /// Doubles the note value, up to a whole note
+    Longer,
+    /// Dotted, double dotted, then plain
+    Dot,
+    /// Triplet, quintuplet, sextuplet, then none
+    Tuplet,
+    /// Removes the notes of the beat
+    Rest,
+}
 
 pub enum Direction {
     Up,
     Down,
     Left,
     Right,
+    /// First measure of the tab
+    Start,
+    /// Last measure of the tab
+    End,
+    HalfPageUp,
+    HalfPageDown,
+    PageUp,
+    PageDown,
+    /// Next beat with notes
+    NextNote,
+    /// Previous beat with notes
+    PreviousNote,
 }
 
 pub trait TabsPrint {
     fn set_tab(&mut self, tracks: Vec<Track>);
+    fn set_song(&mut self, song: Song);
     fn cursor_move(&mut self, direction: Direction);
+    /// Moves the cursor to the start of `measure_num`, counted from 1 as on screen.
+    fn goto_measure(&mut self, measure_num: usize);
+    /// Shows a command being typed on the last line, `None` hides it.
+    fn set_command_line(&mut self, command: Option<String>);
+    fn toggle_notation(&mut self);
+    /// Shows or hides the diagrams of the chords in the track.
+    fn toggle_chord_panel(&mut self);
+    fn set_theme(&mut self, theme: Theme);
+    /// Switches to the next built-in theme.
+    fn cycle_theme(&mut self);
+    fn toggle_voice(&mut self, voice: usize);
+    fn cycle_voice_highlight(&mut self);
+    fn toggle_linear(&mut self);
+    /// Switches between viewing and editing the notes under the cursor.
+    fn toggle_edit(&mut self);
+    /// Turns `effect` on or off for the note under the cursor in edit mode.
+    fn toggle_effect(&mut self, effect: Effect);
+    /// Changes the beat under the cursor in edit mode. The measure is marked when its beats no
+    /// longer fill the time signature.
+    fn edit_beat(&mut self, edit: BeatEdit);
+    /// Shows the next or previous track of the song.
+    fn cycle_track(&mut self, forward: bool);
+    fn toggle_marker_menu(&mut self);
+    /// Opens or closes the list of tracks to switch to.
+    fn toggle_track_menu(&mut self);
+    fn menu_confirm(&mut self);
+    fn menu_cancel(&mut self);
+    /// Starts or stops playback, returns how long to wait before calling `play_step`.
+    fn toggle_play(&mut self) -> Option<Duration>;
+    fn play_step(&mut self) -> Option<Duration>;
+    /// Repeats the measure under the cursor during playback, or stops repeating it.
+    fn toggle_loop(&mut self);
+    /// Shows a list of key bindings as `(keys, action)`, `None` hides it.
+    fn set_help(&mut self, bindings: Option<Vec<(String, String)>>);
+    /// Handles a click or a turn of the scroll wheel.
+    fn mouse(&mut self, mouse: Mouse);
+    /// Redraws if the terminal changed size since the last redraw.
+    fn check_size(&mut self);
 }
 
 pub trait ReadInput {
-    fn read_key(&self) -> Result<Key, std::io::Error>;
+    /// Reads keys and mouse events on a separate thread so the caller can wait with a timeout.
+    fn read_input(&self) -> Receiver<Result<Input, std::io::Error>>;
+}
+
+pub enum Input {
+    Key(Key),
+    Mouse(Mouse),
+}
+
+#[derive(Clone, Copy)]
+pub enum Mouse {
+    /// Left button pressed on a cell of the screen, counted from 0
+    Click {
+        column: usize,
+        row: usize,
+    },
+    ScrollUp,
+    ScrollDown,
+}
+
+/// Lists drawn over the tab that the cursor moves in
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Menu {
+    Markers,
+    Tracks,
+}
+
+/// What a line of the screen shows, to find what a click is on.
+#[derive(Clone, Copy)]
+enum Hit {
+    None,
+    /// The track name starts at this column
+    StatusBar(usize),
+    /// A line of a measure, `string` is `None` above and below the strings
+    Measure {
+        measure: usize,
+        string: Option<usize>,
+    },
+    /// An entry of the open menu
+    MenuEntry(usize),
+}
+
+/// Song details shown in the status bar
+#[derive(Default)]
+struct SongInfo {
+    title: String,
+    artist: String,
+    album: String,
+    tab_author: String,
 }
 
 pub struct Terminal {
     term: Term,
+    song_info: SongInfo,
     // TODO: remove pub
     pub tab: Vec<Track>,
+    /// Index of the track on screen
+    track: usize,
     edit_mode: bool,
+    show_notation: bool,
+    /// Chord diagrams are drawn over the tab
+    show_chords: bool,
+    theme: Theme,
+    visible_voices: Vec<bool>,
+    highlighted_voice: Option<usize>,
+    headers: Vec<MeasureHeader>,
+    tempo: i16,
+    /// Lyrics lines as `(first measure, text)`, to put the syllables on the beats again after
+    /// an edit
+    lyrics_lines: Vec<(u16, String)>,
+    /// Syllables of every lyrics line
+    lyrics: Vec<Vec<Syllable>>,
+    /// Index of the track the lyrics are sung on
+    lyrics_track: usize,
+    roadmap: Vec<usize>,
+    roadmap_pos: usize,
+    linear: bool,
+    sequencer: Option<Sequencer>,
+    /// Roadmap position of the measure repeated during playback
+    loop_pos: Option<usize>,
+    /// Open menu and its selected entry
+    menu: Option<(Menu, usize)>,
+    command_line: Option<String>,
+    help: Option<Vec<(String, String)>>,
     shift: u16,
     cursor_pos: (u16, u16, u8),
+    /// One entry per line written by the last `write_tab`
+    hits: Vec<Hit>,
+    /// Rows and columns of the terminal at the last redraw
+    size: (u16, u16),
+    /// Size of a headless terminal, which has no TTY to ask
+    fixed_size: Option<(u16, u16)>,
+    cache: RenderCache,
 }
 
-#[derive(Clone)]
-struct NoteText {
-    pub note_type: NoteType,
-    pub value: i16,
-    pub is_selected: bool,
-    pub tie_left: bool,
-    pub tie_right: bool,
-    pub hammer_left: bool,
-    pub hammer_right: bool,
-    pub harmonic: bool,
-    pub ghost_note: bool,
-    pub slides: Vec<SlideType>,
-}
+impl Terminal {
+    pub fn new() -> Self {
+        let term = Term::buffered_stdout();
+        term.show_cursor().unwrap();
+        // Alternate screen and mouse reporting with SGR coordinates, turned off on drop
+        term.write_str("\x1b[?1049h\x1b[?1000h\x1b[?1006h").unwrap();
+        Self::with_term(term, None)
+    }
 
-struct MeasureText {
-    beats: Vec<guitarpro::beat::Beat>,
-    strings: Vec<Vec<NoteText>>,
-}
+    /// A terminal of `rows` and `columns` that draws into nothing, to render without a TTY.
+    /// The last screen drawn is returned by `screen`.
+    pub fn headless(rows: u16, columns: u16) -> Self {
+        let null = || {
+            fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open("/dev/null")
+                .expect("error opening /dev/null")
+        };
+        let term = Term::read_write_pair(null(), null());
+        Self::with_term(term, Some((rows, columns)))
+    }
 
-impl MeasureText {
-    pub fn gen_text(&self) -> String {
-        let mut result = vec![String::new(); self.strings.len()];
-        for string_num in 0..self.strings.len() {
-            match string_num {
-                0 => {
-                    result[string_num].push_str("╭");
-                }
-                5 => {
-                    result[string_num].push_str("╰");
-                }
-                _ => {
-                    result[string_num].push_str("├");
-                }
+    fn with_term(term: Term, fixed_size: Option<(u16, u16)>) -> Self {
+        let size = fixed_size.unwrap_or_else(|| term.size());
+        Terminal {
+            term,
+            song_info: SongInfo::default(),
+            tab: Vec::new(),
+            track: 0,
+            edit_mode: false,
+            show_notation: true,
+            show_chords: false,
+            theme: Theme::default(),
+            visible_voices: vec![true; 2],
+            highlighted_voice: None,
+            headers: Vec::new(),
+            tempo: 120,
+            lyrics_lines: Vec::new(),
+            lyrics: Vec::new(),
+            lyrics_track: 0,
+            roadmap: Vec::new(),
+            roadmap_pos: 0,
+            linear: false,
+            sequencer: None,
+            loop_pos: None,
+            menu: None,
+            command_line: None,
+            help: None,
+            shift: 0,
+            cursor_pos: (0, 0, 0),
+            hits: Vec::new(),
+            size,
+            fixed_size,
+            cache: RenderCache::default(),
+        }
+    }
+
+    /// Lines of the last screen drawn, without styles.
+    pub fn screen(&self) -> String {
+        self.cache
+            .screen()
+            .iter()
+            .map(|line| format!("{}\n", console::strip_ansi_codes(line)))
+            .collect()
+    }
+
+    fn term_size(&self) -> (u16, u16) {
+        self.fixed_size.unwrap_or_else(|| self.term.size())
+    }
+
+    /// Layout of the track on screen with the cursor and the view settings.
+    fn layout(&self) -> TabLayout<'_> {
+        let lyrics: &[Vec<Syllable>] = if self.track == self.lyrics_track {
+            &self.lyrics
+        } else {
+            &[]
+        };
+        TabLayout {
+            track: &self.tab[self.track],
+            headers: &self.headers,
+            lyrics,
+            options: self.layout_options(),
+            cursor: Some(Cursor {
+                measure: self.cursor_pos.0 as usize,
+                beat: self.cursor_pos.1 as usize,
+                string: self.cursor_pos.2 as usize,
+                playing: self.sequencer.is_some(),
+            }),
+        }
+    }
+
+    fn layout_options(&self) -> LayoutOptions {
+        LayoutOptions {
+            show_notation: self.show_notation,
+            visible_voices: self.visible_voices.clone(),
+            highlighted_voice: self.highlighted_voice,
+            edit_mode: self.edit_mode,
+            unicode: self.theme.unicode,
+        }
+    }
+
+    fn measure_beats(&self, measure_num: usize) -> MeasureBeats {
+        MeasureBeats::new(
+            &self.tab[self.track].measures[measure_num],
+            &self.visible_voices,
+        )
+    }
+
+    /// Voice and index in the voice of the beat under the cursor. The cursor is on a beat
+    /// merged from the visible voices, this is the beat of the note on the cursor string, or
+    /// of the first visible voice with a beat there.
+    fn cursor_beat(&self) -> Option<(usize, usize)> {
+        let measure_num = self.cursor_pos.0 as usize;
+        let beat_num = self.cursor_pos.1 as usize;
+        let string = self.cursor_pos.2 as i8 + 1;
+        let merged = self.measure_beats(measure_num);
+        let beat = merged.beats.get(beat_num)?;
+        let start = beat.start?;
+        let measure = &self.tab[self.track].measures[measure_num];
+        let note_voice = beat
+            .notes
+            .iter()
+            .position(|note| note.string == string)
+            .map(|note_num| merged.voices[beat_num][note_num]);
+        let voice = note_voice.or_else(|| {
+            (0..measure.voices.len()).find(|voice| {
+                self.visible_voices.get(*voice).copied().unwrap_or(true)
+                    && voices::beat_at(measure, *voice, start).is_some()
+            })
+        })?;
+        Some((voice, voices::beat_at(measure, voice, start)?))
+    }
+
+    /// Note of the track under the cursor.
+    fn cursor_note(&mut self) -> Option<&mut Note> {
+        let (voice, beat_num) = self.cursor_beat()?;
+        let string = self.cursor_pos.2 as i8 + 1;
+        self.tab[self.track].measures[self.cursor_pos.0 as usize].voices[voice].beats[beat_num]
+            .notes
+            .iter_mut()
+            .find(|note| note.string == string)
+    }
+
+    /// Fret of the last note before the cursor on its string, in the voice of the cursor beat.
+    fn previous_fret(&self) -> Option<i16> {
+        let (voice, beat_num) = self.cursor_beat()?;
+        let string = self.cursor_pos.2 as i8 + 1;
+        let measures = &self.tab[self.track].measures[..=self.cursor_pos.0 as usize];
+        measures
+            .iter()
+            .rev()
+            .enumerate()
+            .flat_map(|(measures_back, measure)| {
+                let beats = measure
+                    .voices
+                    .get(voice)
+                    .map_or(&[][..], |voice| &voice.beats);
+                let before = if measures_back == 0 {
+                    beat_num
+                } else {
+                    beats.len()
+                };
+                beats[..before].iter().rev()
+            })
+            .flat_map(|beat| &beat.notes)
+            .find(|note| note.string == string)
+            .map(|note| note.value)
+    }
+
+    /// Puts the lyrics on the beats again after an edit of `measure_num` on the track they are
+    /// sung on, the syllables of the measures after it may move too.
+    fn replace_lyrics(&mut self, measure_num: usize) {
+        if self.track != self.lyrics_track || self.lyrics_lines.is_empty() {
+            return;
+        }
+        self.lyrics = place_lyrics(
+            &self.lyrics_lines,
+            self.tab.get(self.lyrics_track),
+            &self.visible_voices,
+        );
+        self.cache
+            .forget(measure_num..self.tab[self.track].measures.len());
+    }
+
+    /// Forgets what the render cache found with other settings.
+    fn check_cache(&mut self) {
+        self.cache.check_settings(DrawSettings {
+            track: self.track,
+            options: self.layout_options(),
+            theme: self.theme.clone(),
+        });
+    }
+
+    /// Rows `measure_num` takes on screen, laid out only once for the same settings and notes.
+    fn measure_height(&mut self, measure_num: usize) -> usize {
+        if let Some(height) = self.cache.height(measure_num) {
+            return height;
+        }
+        let height = self.layout().measure_height(measure_num);
+        self.cache.insert_height(measure_num, height);
+        height
+    }
+
+    /// Number of measures, starting from `shift`, that fit on the screen
+    fn measures_on_screen(&mut self) -> u16 {
+        self.check_cache();
+        // Minus the status bar, the footer and the line the cursor is left on
+        let mut rows = self.size.0.saturating_sub(3);
+        let mut count = 0;
+        for measure_num in self.shift as usize..self.tab[self.track].measures.len() {
+            let height = self.measure_height(measure_num) as u16;
+            if height > rows && count > 0 {
+                break;
             }
-            for note_num in 0..self.strings[string_num].len() {
-                result[string_num].push_str(self.strings[string_num][note_num].gen_text().as_str());
+            rows = rows.saturating_sub(height);
+            count += 1;
+        }
+        count
+    }
+
+    fn new_sequencer(&self) -> Sequencer {
+        let beats: Vec<Vec<Beat>> = (0..self.tab[self.track].measures.len())
+            .map(|measure_num| self.measure_beats(measure_num).beats)
+            .collect();
+        Sequencer::new(&beats, &self.headers, &self.roadmap, self.tempo)
+    }
+
+    /// Time of every beat of the track, found only once for the same settings and notes.
+    fn timeline(&mut self) -> &Sequencer {
+        self.check_cache();
+        if self.cache.timeline().is_none() {
+            let timeline = self.new_sequencer();
+            self.cache.insert_timeline(timeline);
+        }
+        self.cache.timeline().unwrap()
+    }
+
+    /// Song title, artist, album and tab author, then the tempo, the key and the track. Also
+    /// returns the column the track name starts at.
+    fn status_bar(&self) -> (String, usize) {
+        let info = &self.song_info;
+        let mut song = vec![&info.title, &info.artist, &info.album]
+            .into_iter()
+            .filter(|text| !text.is_empty())
+            .cloned()
+            .collect::<Vec<String>>();
+        if !info.tab_author.is_empty() {
+            song.push(format!("tab by {}", info.tab_author));
+        }
+        let track = &self.tab[self.track];
+        let key = &track.measures[self.cursor_pos.0 as usize].key_signature;
+        let track_text = if track.percussion_track {
+            track.name.clone()
+        } else {
+            // Tuning from the lowest string
+            let tuning: Vec<String> = track
+                .strings
+                .iter()
+                .rev()
+                .map(|(_, pitch)| notation::pitch_name(*pitch as i16))
+                .collect();
+            format!("{}: {}", track.name, tuning.join(" "))
+        };
+        let sections = [
+            song.join(" · "),
+            format!("{} bpm", self.tempo),
+            notation::key_name(key.key, key.is_minor),
+            track_text,
+        ];
+        // After the leading space of the bar and the separator
+        let track_column = console::measure_text_width(&sections[..3].join(" │ ")) + 4;
+        (self.bar(sections.join(" │ ").as_str()), track_column)
+    }
+
+    /// Mode, cursor position and time in the song.
+    fn footer(&mut self) -> String {
+        let mode = if self.sequencer.is_some() {
+            "PLAY"
+        } else if self.edit_mode {
+            "EDIT"
+        } else {
+            "VIEW"
+        };
+        let beats_len = self.measure_beats(self.cursor_pos.0 as usize).beats.len();
+        let roadmap_pos = if self.linear || self.sequencer.is_some() {
+            self.roadmap_pos
+        } else {
+            self.cursor_roadmap_pos()
+        };
+        let beat = self.cursor_pos.1 as usize;
+        let timeline = self.timeline();
+        let times = format!(
+            "{} / {}",
+            time_text(timeline.time_at(roadmap_pos, beat)),
+            time_text(timeline.total_time())
+        );
+        let mut sections = vec![
+            String::from(mode),
+            format!(
+                "measure {}/{} beat {}/{}",
+                self.cursor_pos.0 + 1,
+                self.tab[self.track].measures.len(),
+                self.cursor_pos.1 + 1,
+                beats_len
+            ),
+            times,
+        ];
+        if self.linear {
+            sections.push(String::from("linear"));
+        }
+        if self.loop_pos.is_some() {
+            sections.push(String::from("loop"));
+        }
+        self.bar(sections.join(" │ ").as_str())
+    }
+
+    /// A line across the screen in the status style.
+    fn bar(&self, text: &str) -> String {
+        let width = self.size.1 as usize;
+        let text = console::truncate_str(text, width.saturating_sub(1), "…");
+        let padding = width.saturating_sub(console::measure_text_width(&text) + 1);
+        Theme::paint(
+            &self.theme.status,
+            format!(" {}{}", text, " ".repeat(padding)),
+        )
+    }
+
+    /// Measure index and title of every rehearsal marker
+    fn markers(&self) -> Vec<(usize, &str)> {
+        self.headers
+            .iter()
+            .enumerate()
+            .filter_map(|(measure_num, header)| {
+                Some((measure_num, header.marker.as_ref()?.title.as_str()))
+            })
+            .collect()
+    }
+
+    fn menu_text(&self, menu: Menu, selected: usize) -> Vec<String> {
+        match menu {
+            Menu::Markers => self.marker_menu_text(selected),
+            Menu::Tracks => {
+                let entries: Vec<String> = self
+                    .tab
+                    .iter()
+                    .enumerate()
+                    .map(|(track_num, track)| format!("{: >2}  {}", track_num + 1, track.name))
+                    .collect();
+                self.boxed_list("Tracks", &entries, Some(selected))
             }
         }
-        result.join("\n")
     }
-}
 
-impl NoteText {
-    pub fn new() -> Self {
-        NoteText {
-            note_type: NoteType::Normal,
-            value: -1,
-            is_selected: false,
-            tie_left: false,
-            tie_right: false,
-            hammer_left: false,
-            hammer_right: false,
-            harmonic: false,
-            ghost_note: false,
-            slides: Vec::new(),
-        }
-    }
-    pub fn gen_text(&self) -> String {
-        let mut parts: Vec<String> = vec![String::from("─"); 6];
-        let mut is_value_of_len_two = false;
-        if self.value != -1 {
-            match self.note_type {
-                NoteType::Tie => (),
-                NoteType::Rest => parts[2] = "P".to_string(),
-                NoteType::Dead => parts[2] = "X".to_string(),
-                NoteType::Normal => {
-                    is_value_of_len_two = self.value >= 10;
-                    if is_value_of_len_two {
-                        parts[2] = (self.value / 10).to_string();
-                        parts[3] = (self.value % 10).to_string();
-                    } else {
-                        parts[2] = self.value.to_string();
-                    }
-                }
-                NoteType::Unknown(_) => parts[2] = "u".to_string(),
+    /// Number of entries in `menu`
+    fn menu_len(&self, menu: Menu) -> usize {
+        match menu {
+            Menu::Markers => self.markers().len(),
+            Menu::Tracks => self.tab.len(),
+        }
+    }
+
+    fn marker_menu_text(&self, selected: usize) -> Vec<String> {
+        let markers = self.markers();
+        let mut entries: Vec<String> = markers
+            .iter()
+            .map(|(measure_num, title)| format!("{: >4}  {}", measure_num + 1, title))
+            .collect();
+        if entries.is_empty() {
+            entries.push(String::from("No markers"));
+        }
+        let selected = (!markers.is_empty()).then_some(selected);
+        self.boxed_list("Markers", &entries, selected)
+    }
+
+    fn help_text(&self, bindings: &[(String, String)]) -> Vec<String> {
+        let keys_width = bindings
+            .iter()
+            .map(|(keys, _)| keys.chars().count())
+            .max()
+            .unwrap_or(0);
+        let entries: Vec<String> = bindings
+            .iter()
+            .map(|(keys, action)| format!("{: <width$}  {}", keys, action, width = keys_width))
+            .collect();
+        self.boxed_list("Keys", &entries, None)
+    }
+
+    /// Entries in a box with `title` on its top border, cut to the screen height. The list
+    /// scrolls to keep the `selected` entry, drawn in reverse, on screen.
+    fn boxed_list(&self, title: &str, entries: &[String], selected: Option<usize>) -> Vec<String> {
+        let width = entries
+            .iter()
+            .map(|entry| entry.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(title.chars().count() + 2);
+        let max_entries = self.max_list_entries();
+        let first = (selected.unwrap_or(0) + 1).saturating_sub(max_entries);
+        let mut result = vec![format!(
+            "┌ {} {}┐",
+            title,
+            "─".repeat(width - title.chars().count())
+        )];
+        for (entry_num, entry) in entries.iter().enumerate().skip(first).take(max_entries) {
+            let entry = format!(" {: <width$} ", entry, width = width);
+            if Some(entry_num) == selected {
+                result.push(format!("│{}│", Theme::paint(&self.theme.selection, entry)));
+            } else {
+                result.push(format!("│{}│", entry));
             }
-            if self.ghost_note {
-                parts[1] = "(".to_string();
-                if is_value_of_len_two {
-                    parts[4] = ")".to_string();
-                } else {
-                    parts[3] = ")".to_string();
+        }
+        result.push(format!("└{}┘", "─".repeat(width + 2)));
+        result
+    }
+
+    /// Entries of a boxed list that fit on the screen between its borders
+    fn max_list_entries(&self) -> usize {
+        (self.size.0 as usize).saturating_sub(2).max(1)
+    }
+
+    /// Moves the cursor one measure forward or back, returns false at either end of the tab.
+    /// In linear mode this follows the roadmap, otherwise the written order.
+    fn step_measure(&mut self, forward: bool) -> bool {
+        let moved = if self.linear {
+            let roadmap_pos = if forward {
+                self.roadmap_pos.checked_add(1)
+            } else {
+                self.roadmap_pos.checked_sub(1)
+            };
+            match roadmap_pos.and_then(|pos| Some((pos, *self.roadmap.get(pos)?))) {
+                Some((roadmap_pos, measure)) => {
+                    self.roadmap_pos = roadmap_pos;
+                    self.cursor_pos.0 = measure as u16;
+                    true
                 }
+                None => false,
             }
-            for slide in &self.slides {
-                match slide {
-                    SlideType::None => (),
-                    SlideType::IntoFromAbove => parts[0] = "╲".to_string(),
-                    SlideType::IntoFromBelow => parts[0] = "╱".to_string(),
-                    SlideType::ShiftSlideTo => parts[5] = "╱".to_string(),
-                    SlideType::LegatoSlideTo => parts[5] = "╱".to_string(),
-                    SlideType::OutUpWards => parts[5] = "╱".to_string(),
-                    SlideType::OutDownwards => parts[5] = "╲".to_string(),
-                }
+        } else if forward {
+            if self.cursor_pos.0 < self.tab[self.track].measures.len() as u16 - 1 {
+                self.cursor_pos.0 += 1;
+                true
+            } else {
+                false
             }
-            if self.harmonic && !self.ghost_note {
-                parts[1] = "◇".to_string();
+        } else if self.cursor_pos.0 > 0 {
+            self.cursor_pos.0 -= 1;
+            true
+        } else {
+            false
+        };
+        self.scroll_to_cursor();
+        moved
+    }
+
+    /// Moves the cursor one beat forward or back, going over to the next or previous measure.
+    /// Returns false at either end of the tab.
+    fn step_beat(&mut self, forward: bool) -> bool {
+        let beats_len = self.measure_beats(self.cursor_pos.0 as usize).beats.len() as u16;
+        if forward {
+            if self.cursor_pos.1 + 1 < beats_len {
+                self.cursor_pos.1 += 1;
+            } else if self.step_measure(true) {
+                self.cursor_pos.1 = 0;
+            } else {
+                return false;
             }
-            if self.tie_left {
-                parts[0].push_str("︭");
-                parts[1].push_str("︨︬");
+        } else if self.cursor_pos.1 > 0 {
+            self.cursor_pos.1 -= 1;
+        } else if self.step_measure(false) {
+            self.cursor_pos.1 = (self.measure_beats(self.cursor_pos.0 as usize).beats.len() as u16)
+                .saturating_sub(1);
+        } else {
+            return false;
+        }
+        true
+    }
+
+    /// Moves to the next or previous beat with notes, the cursor stays if there is none.
+    fn step_note(&mut self, forward: bool) {
+        let start = (self.cursor_pos, self.roadmap_pos);
+        while self.step_beat(forward) {
+            let beats = self.measure_beats(self.cursor_pos.0 as usize).beats;
+            if beats
+                .get(self.cursor_pos.1 as usize)
+                .is_some_and(|beat| !beat.notes.is_empty())
+            {
+                return;
             }
-            if self.tie_right {
-                parts[2].push_str("︧︫");
-                for i in 3..6 {
-                    parts[i].push_str("︭");
-                }
+        }
+        (self.cursor_pos, self.roadmap_pos) = start;
+        self.scroll_to_cursor();
+    }
+
+    /// Moves the cursor and the view by `measures`, like scrolling a page.
+    fn step_page(&mut self, measures: u16, forward: bool) {
+        let mut moved = 0;
+        while moved < measures.max(1) && self.step_measure(forward) {
+            moved += 1;
+        }
+        self.cursor_pos.1 = 0;
+        self.shift = if forward {
+            (self.shift + moved).min(self.cursor_pos.0)
+        } else {
+            self.shift.saturating_sub(moved)
+        };
+        self.scroll_to_cursor();
+    }
+
+    /// Puts the cursor on the first beat of `measure_num`.
+    fn jump_to(&mut self, measure_num: usize) {
+        self.cursor_pos.0 = measure_num.min(self.tab[self.track].measures.len() - 1) as u16;
+        self.cursor_pos.1 = 0;
+        self.roadmap_pos = self.cursor_roadmap_pos();
+        self.scroll_to_cursor();
+    }
+
+    fn view_move(&mut self, direction: Direction) {
+        match direction {
+            Direction::Up => {
+                self.step_measure(false);
+                self.cursor_pos.1 = 0;
             }
-            if self.hammer_left && self.hammer_right {
-                for i in 0..6 {
-                    parts[i].push_str("");
-                }
-            } else {
-                if self.hammer_left {
-                    parts[0].push_str("︦");
-                    parts[1].push_str("︡︥");
-                }
-                if self.hammer_right {
-                    parts[2].push_str("︠︤");
-                    for i in 3..6 {
-                        parts[i].push_str("︦");
-                    }
+            Direction::Down => {
+                self.step_measure(true);
+                self.cursor_pos.1 = 0;
+            }
+            Direction::Left => {
+                self.step_beat(false);
+            }
+            Direction::Right => {
+                self.step_beat(true);
+            }
+            Direction::Start => {
+                self.jump_to(0);
+                self.roadmap_pos = 0;
+            }
+            Direction::End => {
+                self.jump_to(self.tab[self.track].measures.len() - 1);
+                if self.linear {
+                    self.roadmap_pos = self.roadmap.len().saturating_sub(1);
                 }
             }
-        } else if self.hammer_left {
-            for i in 0..6 {
-                parts[i].push_str("");
+            Direction::HalfPageUp => {
+                let measures = self.measures_on_screen() / 2;
+                self.step_page(measures, false)
             }
-        }
-        if self.is_selected {
-            parts[2] = style(parts[2].clone())
-                .bg(console::Color::White)
-                .fg(console::Color::Black)
-                .to_string();
-            if is_value_of_len_two {
-                parts[3] = style(parts[3].clone())
-                    .bg(console::Color::White)
-                    .fg(console::Color::Black)
-                    .to_string();
+            Direction::HalfPageDown => {
+                let measures = self.measures_on_screen() / 2;
+                self.step_page(measures, true)
+            }
+            Direction::PageUp => {
+                let measures = self.measures_on_screen();
+                self.step_page(measures, false)
+            }
+            Direction::PageDown => {
+                let measures = self.measures_on_screen();
+                self.step_page(measures, true)
             }
+            Direction::NextNote => self.step_note(true),
+            Direction::PreviousNote => self.step_note(false),
         }
+        self.write_tab();
+    }
 
-        parts.join("")
+    /// Roadmap position of the first time the cursor measure, or the next played one, is played.
+    fn cursor_roadmap_pos(&self) -> usize {
+        self.roadmap
+            .iter()
+            .position(|&measure| measure >= self.cursor_pos.0 as usize)
+            .unwrap_or(0)
     }
-}
 
-impl Terminal {
-    pub fn new() -> Self {
-        let term = Term::buffered_stdout();
-        term.show_cursor().unwrap();
-        Terminal {
-            term,
-            tab: Vec::new(),
-            edit_mode: false,
-            shift: 0,
-            cursor_pos: (0, 0, 0),
+    /// Puts the cursor on the current beat of the sequencer, returns how long it lasts.
+    fn follow_sequencer(&mut self) -> Option<Duration> {
+        let step = self
+            .sequencer
+            .as_ref()
+            .and_then(|sequencer| sequencer.current());
+        let (roadmap_pos, measure, beat, duration) = match step {
+            Some(step) => (step.roadmap_pos, step.measure, step.beat, step.duration),
+            None => {
+                self.sequencer = None;
+                return None;
+            }
+        };
+        self.roadmap_pos = roadmap_pos;
+        self.cursor_pos.0 = measure as u16;
+        self.cursor_pos.1 = beat as u16;
+        self.scroll_to_cursor();
+        self.write_tab();
+        Some(duration)
+    }
+
+    /// Switches to the track at `track_num`, keeping the cursor on the same beat.
+    fn show_track(&mut self, track_num: usize) {
+        self.track = track_num;
+        let last_string = self.tab[self.track].strings.len().saturating_sub(1) as u8;
+        self.cursor_pos.2 = self.cursor_pos.2.min(last_string);
+        let beats_len = self.measure_beats(self.cursor_pos.0 as usize).beats.len() as u16;
+        self.cursor_pos.1 = self.cursor_pos.1.min(beats_len.saturating_sub(1));
+        // The beats are different on every track
+        self.sequencer = None;
+        self.scroll_to_cursor();
+        self.write_tab();
+    }
+
+    fn scroll_to_cursor(&mut self) {
+        if self.shift > self.cursor_pos.0 {
+            self.shift = self.cursor_pos.0;
+        }
+        while self.shift + self.measures_on_screen() < self.cursor_pos.0 + 1 {
+            self.shift += 1;
         }
     }
 
-    fn write_tab(&self) {
-        self.term
-            .clear_last_lines(self.term.size().0 as usize)
-            .unwrap();
-        self.term.flush().expect("error writing tab");
-        let mut measures: Vec<MeasureText> = Vec::new();
-        for measure_num in (self.shift) as usize..((self.term.size().0 / 8) + self.shift) as usize {
-            let measure = MeasureText {
-                beats: self.tab[0].measures[measure_num].voices[0].beats.clone(),
-                strings: vec![
-                    vec![
-                        NoteText::new();
-                        self.tab[0].measures[measure_num].voices[0].beats.len()
-                    ];
-                    self.tab[0].strings.len()
-                ],
+    /// What the text of `measure_num` depends on when the measures in `on_screen` are drawn.
+    fn measure_key(&self, measure_num: usize, on_screen: &Range<usize>) -> MeasureKey {
+        MeasureKey {
+            cursor: (self.cursor_pos.0 as usize == measure_num).then_some((
+                self.cursor_pos.1,
+                self.cursor_pos.2,
+                self.sequencer.is_some(),
+            )),
+            first_on_screen: measure_num == on_screen.start,
+            last_on_screen: measure_num + 1 == on_screen.end,
+        }
+    }
+
+    fn write_tab(&mut self) {
+        self.size = self.term_size();
+        self.check_cache();
+        let on_screen = self.shift as usize..(self.shift + self.measures_on_screen()) as usize;
+        let stale: Vec<(usize, MeasureKey)> = on_screen
+            .clone()
+            .map(|measure_num| (measure_num, self.measure_key(measure_num, &on_screen)))
+            .filter(|(measure_num, key)| self.cache.get(*measure_num, key).is_none())
+            .collect();
+        // Ties and hammer-ons across bar lines need the measures next to the stale ones
+        let built = match (stale.first(), stale.last()) {
+            (Some((first, _)), Some((last, _))) => {
+                first.saturating_sub(1).max(on_screen.start)..(last + 2).min(on_screen.end)
+            }
+            _ => 0..0,
+        };
+        let layouts = self.layout().measures(built.clone());
+        for (measure_num, key) in stale {
+            let layout = &layouts[measure_num - built.start];
+            let lines = layout
+                .grid
+                .rows
+                .iter()
+                .map(|row| self.theme.paint_row(row))
+                .collect();
+            let strings = layout.strings.clone();
+            self.cache
+                .insert(measure_num, key, MeasureLines { lines, strings });
+        }
+
+        let (status_bar, track_column) = self.status_bar();
+        let mut lines: Vec<String> = vec![status_bar];
+        let mut hits: Vec<Hit> = vec![Hit::StatusBar(track_column)];
+        for measure_num in on_screen.clone() {
+            let key = self.measure_key(measure_num, &on_screen);
+            let Some(measure) = self.cache.get(measure_num, &key) else {
+                continue;
             };
-            measures.push(measure);
-        }
-        for measure_num in 0..(self.term.size().0 / 8) as usize {
-            for beat_num in 0..measures[measure_num].beats.len() {
-                for note_num in 0..measures[measure_num].beats[beat_num].notes.len() {
-                    let note_str =
-                        measures[measure_num].beats[beat_num].notes[note_num].string as usize - 1;
-                    if measures[measure_num].beats[beat_num].notes[note_num].kind == NoteType::Tie {
-                        measures[measure_num].strings[note_str][beat_num].tie_left = true;
-                        match measures[measure_num].strings[note_str].get_mut(if beat_num == 0 {
-                            usize::MAX
-                        } else {
-                            beat_num - 1
-                        }) {
-                            Some(note_text) => note_text.tie_right = true,
-                            None => {
-                                if measure_num != 0 {
-                                    match measures.get_mut(measure_num - 1) {
-                                        Some(m) => {
-                                            match m.strings[note_str].get_mut(m.beats.len() - 1) {
-                                                Some(note_text) => note_text.tie_right = true,
-                                                None => (),
-                                            }
-                                        }
-                                        None => (),
-                                    }
-                                }
-                            }
-                        };
-                    }
-                    if measures[measure_num].beats[beat_num].notes[note_num]
-                        .effect
-                        .hammer
-                    {
-                        measures[measure_num].strings[note_str][beat_num].hammer_right = true;
-                        match measures[measure_num].strings[note_str].get_mut(beat_num + 1) {
-                            Some(note) => note.hammer_left = true,
-                            None => match measures.get_mut(measure_num + 1) {
-                                None => (),
-                                Some(m) => m.strings[note_num][0].hammer_left = true,
-                            },
-                        }
-                    }
-                    // Transfering data to NoteText
-                    {
-                        let note_value =
-                            measures[measure_num].beats[beat_num].notes[note_num].value;
-                        measures[measure_num].strings[note_str][beat_num].value = note_value;
-                    }
-                    {
-                        let note_slides = measures[measure_num].beats[beat_num].notes[note_num]
-                            .effect
-                            .slides
-                            .clone();
-                        measures[measure_num].strings[note_str][beat_num].slides = note_slides;
-                    }
-                    {
-                        // NoteType does not implement Clone trait, so...
-                        match measures[measure_num].beats[beat_num].notes[note_num].kind {
-                            NoteType::Tie => {
-                                measures[measure_num].strings[note_str][beat_num].note_type =
-                                    NoteType::Tie
-                            }
-                            NoteType::Rest => {
-                                measures[measure_num].strings[note_str][beat_num].note_type =
-                                    NoteType::Rest
-                            }
-                            NoteType::Dead => {
-                                measures[measure_num].strings[note_str][beat_num].note_type =
-                                    NoteType::Dead
-                            }
-                            NoteType::Normal => {
-                                measures[measure_num].strings[note_str][beat_num].note_type =
-                                    NoteType::Normal
-                            }
-                            NoteType::Unknown(x) => {
-                                measures[measure_num].strings[note_str][beat_num].note_type =
-                                    NoteType::Unknown(x)
-                            }
-                        }
-                    }
-                    if measures[measure_num].beats[beat_num].notes[note_num]
-                        .effect
-                        .harmonic
-                        != None
-                    {
-                        measures[measure_num].strings[note_num][beat_num].harmonic = true;
-                    }
-                    measures[measure_num].strings[note_str][beat_num].ghost_note =
-                        measures[measure_num].beats[beat_num].notes[note_num]
-                            .effect
-                            .ghost_note;
-                    measures[measure_num].strings[note_str][beat_num].is_selected =
-                        self.cursor_pos == (measure_num as u16, beat_num as u16, note_str as u8);
+            for (line_num, line) in measure.lines.iter().enumerate() {
+                lines.push(line.clone());
+                hits.push(Hit::Measure {
+                    measure: measure_num,
+                    string: measure
+                        .strings
+                        .contains(&line_num)
+                        .then(|| line_num - measure.strings.start),
+                });
+            }
+        }
+        let overlay = match (&self.help, self.menu) {
+            (Some(bindings), _) => Some(self.help_text(bindings)),
+            (None, Some((menu, selected))) => Some(self.menu_text(menu, selected)),
+            (None, None) if self.show_chords => {
+                let width = self.size.1 as usize;
+                let panel = chords::panel(&self.layout().chord_shapes(), width);
+                Some(self.boxed_list("Chords", &panel, None))
+            }
+            (None, None) => None,
+        };
+        if let Some(overlay) = overlay {
+            // Menus are drawn over the first lines of the tab
+            let menu_entries = match (&self.help, self.menu) {
+                (None, Some((_, selected))) => {
+                    let first = (selected + 1).saturating_sub(self.max_list_entries());
+                    // Between the top and bottom borders
+                    first..first + overlay.len().saturating_sub(2)
                 }
-                if !self.edit_mode
-                    && self.cursor_pos.0 == measure_num as u16 + self.shift
-                    && self.cursor_pos.1 == beat_num as u16
-                {
-                    for string_num in 0..self.tab[0].strings.len() {
-                        measures[measure_num].strings[string_num][beat_num].is_selected = true;
-                    }
+                _ => 0..0,
+            };
+            for line_num in 0..overlay.len() {
+                let entry = menu_entries.start + line_num;
+                let hit = if line_num > 0 && menu_entries.contains(&(entry - 1)) {
+                    Hit::MenuEntry(entry - 1)
+                } else {
+                    Hit::None
+                };
+                match hits.get_mut(line_num) {
+                    Some(line_hit) => *line_hit = hit,
+                    None => hits.push(hit),
                 }
             }
-            // let mut string_durations = String::from(" ");
-            // for beat in &self.tab[0].measures[measure_num as usize].voices[0].beats {
-            //     if beat.duration.dotted {
-            //         string_durations.push_str(
-            //             format!("{: ^5}", beat.duration.value.to_string() + ".").as_str(),
-            //         );
-            //     } else {
-            //         string_durations.push_str(format!("{: ^6}", beat.duration.value).as_str());
-            //     }
-            //     // string_durations.push('-');
-            //     // string_durations.push_str(beat.duration.tuplet_times.to_string().as_str());
-            //     // string_durations.push('-');
-            //     // string_durations.push_str(beat.duration.tuplet_enters.to_string().as_str());
-            //     // string_durations.push('-');
-            //     // string_durations.push_str(beat.duration.min_time.to_string().as_str());
-            // }
-            // measure.push(string_durations);
-        }
-
-        self.term
-            .write_line(
-                measures
-                    .into_iter()
-                    .map(|measure_iter| measure_iter.gen_text())
-                    .collect::<Vec<String>>()
-                    .join("\n")
-                    .as_str(),
-            )
-            .unwrap();
+            for (line_num, menu_line) in overlay.into_iter().enumerate() {
+                match lines.get_mut(line_num) {
+                    Some(line) => *line = menu_line,
+                    None => lines.push(menu_line),
+                }
+            }
+        }
+        // The footer goes on the last line but one, the command line takes its place
+        let footer_line = (self.size.0 as usize).saturating_sub(2);
+        lines.truncate(footer_line);
+        lines.resize(footer_line, String::new());
+        hits.truncate(footer_line);
+        hits.resize(footer_line, Hit::None);
+        self.hits = hits;
+        match self.command_line.clone() {
+            Some(command) => lines.push(command),
+            None => lines.push(self.footer()),
+        }
+        // Lines are cut to the width so they do not wrap, and only the ones that changed are
+        // written over the previous screen
+        let width = self.size.1 as usize;
+        let lines: Vec<String> = lines
+            .iter()
+            .map(|line| self.theme.text(&console::truncate_str(line, width, "")))
+            .collect();
+        let rows = lines.len();
+        for (row, line) in self.cache.changed_rows(lines, self.size) {
+            self.term.move_cursor_to(0, row).unwrap();
+            self.term.clear_line().unwrap();
+            self.term.write_str(line).unwrap();
+        }
+        // The cursor is left on the last line
+        self.term.move_cursor_to(0, rows).unwrap();
+        self.term.clear_to_end_of_screen().unwrap();
 
         self.term.flush().expect("error writing tab");
     }
 }
 
+impl Default for Terminal {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.term.write_str("\x1b[?1006l\x1b[?1000l\x1b[?1049l");
+        let _ = self.term.flush();
+    }
+}
+
 impl ReadInput for Terminal {
-    fn read_key(&self) -> Result<Key, std::io::Error> {
-        self.term.read_key()
+    fn read_input(&self) -> Receiver<Result<Input, std::io::Error>> {
+        let (sender, receiver) = mpsc::channel();
+        let term = self.term.clone();
+        thread::spawn(move || loop {
+            let input = match term.read_key() {
+                // The terminal does not know mouse reports, they come as an unknown sequence
+                // followed by the rest of the report
+                Ok(Key::UnknownEscSeq(seq)) if seq.starts_with(&['[', '<']) => {
+                    match read_mouse(&term, &seq[2..]) {
+                        Some(mouse) => Ok(Input::Mouse(mouse)),
+                        None => continue,
+                    }
+                }
+                key => key.map(Input::Key),
+            };
+            if sender.send(input).is_err() {
+                break;
+            }
+        });
+        receiver
+    }
+}
+
+/// Reads the rest of an SGR mouse report like `\x1b[<0;12;5M` after its first characters.
+/// `None` for the events that are not handled, like releasing a button.
+fn read_mouse(term: &Term, start: &[char]) -> Option<Mouse> {
+    let mut report: String = start.iter().collect();
+    let pressed = loop {
+        match term.read_key().ok()? {
+            Key::Char('M') => break true,
+            Key::Char('m') => break false,
+            Key::Char(ch) => report.push(ch),
+            _ => return None,
+        }
+    };
+    let mut numbers = report.split(';').map(|number| number.parse::<usize>().ok());
+    let (button, column, row) = (numbers.next()??, numbers.next()??, numbers.next()??);
+    // Without the Shift, Alt and Ctrl bits
+    match button & !0b11100 {
+        0 if pressed => Some(Mouse::Click {
+            column: column.saturating_sub(1),
+            row: row.saturating_sub(1),
+        }),
+        64 => Some(Mouse::ScrollUp),
+        65 => Some(Mouse::ScrollDown),
+        _ => None,
     }
 }
 
 impl TabsPrint for Terminal {
     fn set_tab(&mut self, tracks: Vec<Track>) {
         self.tab.append(&mut tracks.clone());
+        self.roadmap = if self.headers.is_empty() {
+            (0..self.tab[self.track].measures.len()).collect()
+        } else {
+            roadmap::roadmap(&self.headers)
+        };
         self.write_tab();
     }
 
+    fn set_song(&mut self, song: Song) {
+        self.song_info = SongInfo {
+            title: song.name,
+            artist: song.artist,
+            album: song.album,
+            tab_author: if song.writer.is_empty() {
+                song.transcriber
+            } else {
+                song.writer
+            },
+        };
+        // Lyrics are bound to a track counted from 1
+        self.lyrics_track = (song.lyrics.track_choice as usize)
+            .saturating_sub(1)
+            .min(song.tracks.len().saturating_sub(1));
+        self.lyrics_lines = song
+            .lyrics
+            .lines
+            .into_iter()
+            .map(|(_, first_measure, text)| (first_measure, text))
+            .collect();
+        self.headers = song.measure_headers;
+        self.tempo = song.tempo;
+        self.lyrics = place_lyrics(
+            &self.lyrics_lines,
+            song.tracks.get(self.lyrics_track),
+            &self.visible_voices,
+        );
+        self.set_tab(song.tracks);
+    }
+
     fn cursor_move(&mut self, direction: Direction) {
-        if !self.edit_mode {
+        if let Some((menu, selected)) = self.menu {
+            let last = self.menu_len(menu).saturating_sub(1);
+            let selected = match direction {
+                Direction::Up | Direction::Left | Direction::PreviousNote => {
+                    selected.saturating_sub(1)
+                }
+                Direction::Down | Direction::Right | Direction::NextNote => {
+                    (selected + 1).min(last)
+                }
+                Direction::Start | Direction::HalfPageUp | Direction::PageUp => 0,
+                Direction::End | Direction::HalfPageDown | Direction::PageDown => last,
+            };
+            self.menu = Some((menu, selected));
+            self.write_tab();
+        } else if !self.edit_mode {
+            self.view_move(direction);
+        } else {
             match direction {
                 Direction::Up => {
-                    if self.cursor_pos.0 > 0 {
-                        self.cursor_pos.0 -= 1;
-                    }
-                    if self.shift > self.cursor_pos.0 && self.shift > 0 {
-                        self.shift -= 1;
-                    }
-                    self.cursor_pos.1 = 0;
+                    self.cursor_pos.2 = self.cursor_pos.2.saturating_sub(1);
                     self.write_tab();
                 }
                 Direction::Down => {
-                    if self.cursor_pos.0 < self.tab[0].measures.len() as u16 - 1 {
-                        self.cursor_pos.0 += 1;
-                    }
-                    if self.shift + (self.term.size().0 / 8) < self.cursor_pos.0 + 1
-                        && self.shift < self.tab[0].measures.len() as u16
-                    {
-                        self.shift += 1;
-                    }
-                    self.cursor_pos.1 = 0;
+                    let last_string = self.tab[self.track].strings.len().saturating_sub(1) as u8;
+                    self.cursor_pos.2 = (self.cursor_pos.2 + 1).min(last_string);
                     self.write_tab();
                 }
                 Direction::Left => {
-                    if self.cursor_pos.1 == 0 {
-                        if self.cursor_pos.0 > 0 {
-                            self.cursor_pos.0 -= 1;
-                        }
-                        if self.shift > self.cursor_pos.0 && self.shift > 0 {
-                            self.shift -= 1;
-                        }
-                        self.cursor_pos.1 = self.tab[0].measures[self.cursor_pos.0 as usize].voices
-                            [0]
-                        .beats
-                        .len() as u16
-                            - 1;
-                    } else {
-                        self.cursor_pos.1 -= 1;
-                    }
+                    self.step_beat(false);
                     self.write_tab();
                 }
                 Direction::Right => {
-                    if self.cursor_pos.1
-                        == self.tab[0].measures[self.cursor_pos.0 as usize].voices[0]
-                            .beats
-                            .len() as u16
-                            - 1
-                    {
-                        if self.cursor_pos.0 < self.tab[0].measures.len() as u16 - 1 {
-                            self.cursor_pos.0 += 1;
-                        }
-                        if self.shift + (self.term.size().0 / 8) < self.cursor_pos.0 + 1
-                            && self.shift < self.tab[0].measures.len() as u16
-                        {
-                            self.shift += 1;
-                        }
-                        self.cursor_pos.1 = 0;
-                    } else {
-                        self.cursor_pos.1 += 1;
-                    }
+                    self.step_beat(true);
                     self.write_tab();
                 }
+                direction => self.view_move(direction),
+            }
+        }
+    }
+
+    fn goto_measure(&mut self, measure_num: usize) {
+        self.jump_to(measure_num.saturating_sub(1));
+        self.write_tab();
+    }
+
+    fn set_command_line(&mut self, command: Option<String>) {
+        self.command_line = command;
+        self.write_tab();
+    }
+
+    fn toggle_notation(&mut self) {
+        self.show_notation = !self.show_notation;
+        self.scroll_to_cursor();
+        self.write_tab();
+    }
+
+    fn toggle_chord_panel(&mut self) {
+        self.show_chords = !self.show_chords;
+        self.write_tab();
+    }
+
+    fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.write_tab();
+    }
+
+    fn cycle_theme(&mut self) {
+        self.set_theme(self.theme.next());
+    }
+
+    fn toggle_voice(&mut self, voice: usize) {
+        if voice >= self.visible_voices.len() {
+            return;
+        }
+        self.visible_voices[voice] = !self.visible_voices[voice];
+        // At least one voice stays on screen
+        if !self.visible_voices.contains(&true) {
+            self.visible_voices[voice] = true;
+        }
+        // The lyrics follow the first voice on screen
+        self.lyrics = place_lyrics(
+            &self.lyrics_lines,
+            self.tab.get(self.lyrics_track),
+            &self.visible_voices,
+        );
+        let beats_len = self.measure_beats(self.cursor_pos.0 as usize).beats.len() as u16;
+        self.cursor_pos.1 = self.cursor_pos.1.min(beats_len.saturating_sub(1));
+        self.scroll_to_cursor();
+        self.write_tab();
+    }
+
+    fn cycle_voice_highlight(&mut self) {
+        self.highlighted_voice = match self.highlighted_voice {
+            None => Some(0),
+            Some(voice) if voice + 1 < self.visible_voices.len() => Some(voice + 1),
+            Some(_) => None,
+        };
+        self.write_tab();
+    }
+
+    fn toggle_linear(&mut self) {
+        self.linear = !self.linear;
+        if self.linear {
+            self.roadmap_pos = self.cursor_roadmap_pos();
+        }
+        self.write_tab();
+    }
+
+    fn toggle_edit(&mut self) {
+        self.edit_mode = !self.edit_mode;
+        self.write_tab();
+    }
+
+    fn toggle_effect(&mut self, effect: Effect) {
+        if !self.edit_mode {
+            return;
+        }
+        // Files give a tied note the fret of the note it holds
+        let previous_fret = match effect {
+            Effect::Tie => self.previous_fret(),
+            _ => None,
+        };
+        let Some(note) = self.cursor_note() else {
+            return;
+        };
+        let toggle_kind = |note: &mut Note, kind: NoteType| {
+            note.kind = if note.kind == kind {
+                NoteType::Normal
+            } else {
+                kind
+            };
+        };
+        match effect {
+            Effect::Hammer => note.effect.hammer = !note.effect.hammer,
+            Effect::SlideIn => {
+                let slides_in = [SlideType::IntoFromBelow, SlideType::IntoFromAbove];
+                cycle_slide(note, &slides_in, &slides_in);
+            }
+            Effect::SlideOut => cycle_slide(
+                note,
+                &[
+                    SlideType::ShiftSlideTo,
+                    SlideType::LegatoSlideTo,
+                    SlideType::OutDownwards,
+                    SlideType::OutUpWards,
+                ],
+                &[SlideType::ShiftSlideTo, SlideType::OutDownwards],
+            ),
+            Effect::Tie => {
+                toggle_kind(note, NoteType::Tie);
+                if let Some(fret) = previous_fret.filter(|_| note.kind == NoteType::Tie) {
+                    note.value = fret;
+                }
             }
+            Effect::GhostNote => note.effect.ghost_note = !note.effect.ghost_note,
+            Effect::Harmonic => {
+                note.effect.harmonic = match note.effect.harmonic {
+                    Some(_) => None,
+                    None => Some(HarmonicEffect::default()),
+                }
+            }
+            Effect::DeadNote => toggle_kind(note, NoteType::Dead),
+            Effect::PalmMute => note.effect.palm_mute = !note.effect.palm_mute,
+            Effect::Vibrato => note.effect.vibrato = !note.effect.vibrato,
+            Effect::Bend => {
+                note.effect.bend = match note.effect.bend {
+                    Some(_) => None,
+                    None => Some(full_bend()),
+                }
+            }
+        }
+        // Ties, hammer-ons and slides are drawn from the notes next to them
+        let measure_num = self.cursor_pos.0 as usize;
+        self.cache
+            .forget(measure_num.saturating_sub(1)..measure_num + 2);
+        // Tied notes are not sung, the syllables after a note move when it is tied or untied
+        if matches!(effect, Effect::Tie | Effect::DeadNote) {
+            self.replace_lyrics(measure_num);
+        }
+        self.write_tab();
+    }
+
+    fn edit_beat(&mut self, edit: BeatEdit) {
+        if !self.edit_mode {
+            return;
+        }
+        let Some((voice, beat_num)) = self.cursor_beat() else {
+            return;
+        };
+        let measure_num = self.cursor_pos.0 as usize;
+        let beat = &mut self.tab[self.track].measures[measure_num].voices[voice].beats[beat_num];
+        let duration = &mut beat.duration;
+        match edit {
+            BeatEdit::Shorter => duration.value = (duration.value * 2).min(64),
+            BeatEdit::Longer => duration.value = (duration.value / 2).max(1),
+            BeatEdit::Dot => {
+                (duration.dotted, duration.double_dotted) =
+                    match (duration.dotted, duration.double_dotted) {
+                        (false, false) => (true, false),
+                        (true, false) => (false, true),
+                        _ => (false, false),
+                    }
+            }
+            BeatEdit::Tuplet => {
+                (duration.tuplet_enters, duration.tuplet_times) =
+                    match (duration.tuplet_enters, duration.tuplet_times) {
+                        (3, 2) => (5, 4),
+                        (5, 4) => (6, 4),
+                        (enters, times) if enters == times => (3, 2),
+                        _ => (1, 1),
+                    }
+            }
+            BeatEdit::Rest => {
+                beat.notes.clear();
+                beat.status = BeatStatus::Rest;
+            }
+        }
+        // Beats of other voices may now start with this one or apart from it
+        let beats_len = self.measure_beats(measure_num).beats.len() as u16;
+        self.cursor_pos.1 = self.cursor_pos.1.min(beats_len.saturating_sub(1));
+        // Playback was timed from the old durations
+        self.sequencer = None;
+        self.cache
+            .forget(measure_num.saturating_sub(1)..measure_num + 2);
+        self.replace_lyrics(measure_num);
+        self.write_tab();
+    }
+
+    fn cycle_track(&mut self, forward: bool) {
+        let tracks = self.tab.len();
+        if tracks == 0 {
+            return;
+        }
+        self.show_track(if forward {
+            (self.track + 1) % tracks
         } else {
-            match direction {
-                Direction::Up => {
-                    self.cursor_pos.2 -= 1;
-                    self.write_tab();
+            (self.track + tracks - 1) % tracks
+        });
+    }
+
+    fn toggle_play(&mut self) -> Option<Duration> {
+        if self.sequencer.take().is_some() {
+            return None;
+        }
+        let mut sequencer = self.new_sequencer();
+        let roadmap_pos = if self.linear {
+            self.roadmap_pos
+        } else {
+            self.cursor_roadmap_pos()
+        };
+        sequencer.seek(roadmap_pos, self.cursor_pos.1 as usize);
+        self.sequencer = Some(sequencer);
+        self.follow_sequencer()
+    }
+
+    fn play_step(&mut self) -> Option<Duration> {
+        let sequencer = self.sequencer.as_mut()?;
+        let roadmap_pos = sequencer.advance().map(|step| step.roadmap_pos);
+        if let Some(loop_pos) = self.loop_pos {
+            if roadmap_pos != Some(loop_pos) {
+                sequencer.seek(loop_pos, 0);
+            }
+        }
+        self.follow_sequencer()
+    }
+
+    fn toggle_loop(&mut self) {
+        self.loop_pos = match self.loop_pos {
+            Some(_) => None,
+            None if self.linear || self.sequencer.is_some() => Some(self.roadmap_pos),
+            None => Some(self.cursor_roadmap_pos()),
+        };
+        self.write_tab();
+    }
+
+    fn set_help(&mut self, bindings: Option<Vec<(String, String)>>) {
+        self.help = bindings;
+        self.write_tab();
+    }
+
+    fn toggle_marker_menu(&mut self) {
+        self.menu = match self.menu {
+            Some((Menu::Markers, _)) => None,
+            _ => Some((
+                Menu::Markers,
+                self.markers()
+                    .iter()
+                    .rposition(|(measure_num, _)| *measure_num <= self.cursor_pos.0 as usize)
+                    .unwrap_or(0),
+            )),
+        };
+        self.write_tab();
+    }
+
+    fn toggle_track_menu(&mut self) {
+        self.menu = match self.menu {
+            Some((Menu::Tracks, _)) => None,
+            _ => Some((Menu::Tracks, self.track)),
+        };
+        self.write_tab();
+    }
+
+    fn menu_confirm(&mut self) {
+        let Some((menu, selected)) = self.menu.take() else {
+            return;
+        };
+        match menu {
+            Menu::Markers => {
+                if let Some(&(measure_num, _)) = self.markers().get(selected) {
+                    // Put the section on top of the screen
+                    self.shift = measure_num as u16;
+                    self.jump_to(measure_num);
                 }
-                Direction::Down => {
-                    self.cursor_pos.2 += 1;
-                    self.write_tab();
+            }
+            Menu::Tracks if selected < self.tab.len() => return self.show_track(selected),
+            Menu::Tracks => (),
+        }
+        self.write_tab();
+    }
+
+    fn menu_cancel(&mut self) {
+        if self.menu.take().is_some() {
+            self.write_tab();
+        }
+    }
+
+    fn check_size(&mut self) {
+        if self.term_size() != self.size {
+            self.size = self.term_size();
+            self.scroll_to_cursor();
+            self.write_tab();
+        }
+    }
+
+    fn mouse(&mut self, mouse: Mouse) {
+        let (column, row) = match mouse {
+            Mouse::Click { column, row } => (column, row),
+            Mouse::ScrollUp => {
+                self.shift = self.shift.saturating_sub(1);
+                return self.write_tab();
+            }
+            Mouse::ScrollDown => {
+                let last = self.tab[self.track].measures.len().saturating_sub(1) as u16;
+                self.shift = (self.shift + 1).min(last);
+                return self.write_tab();
+            }
+        };
+        match self.hits.get(row).copied() {
+            Some(Hit::StatusBar(track_column)) if column >= track_column => {
+                self.toggle_track_menu()
+            }
+            Some(Hit::MenuEntry(entry)) => {
+                if let Some((menu, _)) = self.menu {
+                    self.menu = Some((menu, entry));
+                    self.menu_confirm();
                 }
-                Direction::Left => {
-                    self.cursor_pos.1 -= 1;
-                    self.write_tab();
+            }
+            Some(Hit::Measure { measure, string }) => {
+                let beats_len = self.measure_beats(measure).beats.len();
+                let beat = MeasureLayout::beat_at_column(column).min(beats_len.saturating_sub(1));
+                self.cursor_pos.0 = measure as u16;
+                self.cursor_pos.1 = beat as u16;
+                if let Some(string) = string {
+                    self.cursor_pos.2 = string as u8;
                 }
-                Direction::Right => {
-                    self.cursor_pos.1 += 1;
-                    self.write_tab();
+                self.roadmap_pos = self.cursor_roadmap_pos();
+                match self.sequencer.as_mut() {
+                    Some(sequencer) => {
+                        sequencer.seek(self.roadmap_pos, beat);
+                        self.follow_sequencer();
+                    }
+                    None => self.write_tab(),
                 }
             }
+            _ => (),
         }
     }
 }
+
+/// Time as minutes and seconds, e.g. "1:05"
+fn time_text(time: Duration) -> String {
+    let seconds = time.as_secs();
+    format!("{}:{:02}", seconds / 60, seconds % 60)
+}
+
+/// Syllables of every lyrics line on the beats of the `visible` voices of `track`.
+fn place_lyrics(
+    lines: &[(u16, String)],
+    track: Option<&Track>,
+    visible: &[bool],
+) -> Vec<Vec<Syllable>> {
+    let Some(track) = track else {
+        return Vec::new();
+    };
+    lines
+        .iter()
+        .map(|(first_measure, text)| lyrics::syllables(text, *first_measure, track, visible))
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+/// Replaces the slide of `note` that is one of `slides` by the next one in `cycle`, after the
+/// last one or one not in `cycle` the note has none.
+fn cycle_slide(note: &mut Note, slides: &[SlideType], cycle: &[SlideType]) {
+    let current = note
+        .effect
+        .slides
+        .iter()
+        .find(|slide| slides.contains(slide))
+        .cloned();
+    note.effect.slides.retain(|slide| !slides.contains(slide));
+    let next = match current {
+        None => cycle.first(),
+        Some(current) => cycle
+            .iter()
+            .position(|slide| *slide == current)
+            .and_then(|pos| cycle.get(pos + 1)),
+    };
+    note.effect.slides.extend(next.cloned());
+}
+
+/// Bend up a whole tone in the first half of the beat, then held
+fn full_bend() -> BendEffect {
+    let point = |position: u8, value: i8| BendPoint {
+        position,
+        value,
+        vibrato: false,
+    };
+    BendEffect {
+        kind: BendType::Bend,
+        value: 100,
+        points: vec![point(0, 0), point(6, 4), point(12, 4)],
+        ..BendEffect::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mikasa() -> Song {
+        let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("resources")
+            .join("Veil Of Maya-Mikasa.gp5");
+        let mut song = Song::default();
+        song.read_gp5(&fs::read(path).unwrap());
+        song
+    }
+
+    #[test]
+    fn cursor_moves_reuse_the_heights_and_the_timeline() {
+        let mut terminal = Terminal::headless(24, 80);
+        terminal.set_song(mikasa());
+        terminal.toggle_notation();
+        assert!(terminal.screen().contains("\n2\n"));
+        assert!(terminal.screen().contains(" 0:00 / 3:05"));
+
+        // Found again, these would be replaced by the right ones
+        terminal.cache.insert_height(1, 1000);
+        terminal
+            .cache
+            .insert_timeline(Sequencer::new(&[], &[], &[], 120));
+        terminal.cursor_move(Direction::Right);
+        let screen = terminal.screen();
+        assert!(screen.contains("beat 2/2 │ 0:00 / 0:00"), "{}", screen);
+        assert!(!screen.contains("\n2\n"), "{}", screen);
+
+        // Other settings find them again
+        terminal.toggle_edit();
+        let screen = terminal.screen();
+        assert!(screen.contains("beat 2/2 │ 0:00 / 3:05"), "{}", screen);
+        assert!(screen.contains("\n2\n"), "{}", screen);
+
+        // And so do edits
+        terminal
+            .cache
+            .insert_timeline(Sequencer::new(&[], &[], &[], 120));
+        terminal.edit_beat(BeatEdit::Shorter);
+        let screen = terminal.screen();
+        assert!(screen.contains("1 (too short)"), "{}", screen);
+        assert!(screen.contains("beat 2/2 │ 0:00 / 3:05"), "{}", screen);
+    }
+}
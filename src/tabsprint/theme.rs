@@ -0,0 +1,164 @@
+use std::fmt::Display;
+
+use console::{Color, Style};
+
+use crate::layout::{self, Cell, Role};
+
+/// Names of the built-in themes, in the order they are cycled through.
+pub const THEMES: [&str; 4] = ["default", "dark", "light", "plain"];
+
+/// Styles of the parts of the tab, and whether it is drawn with Unicode glyphs.
+#[derive(Clone, PartialEq)]
+pub struct Theme {
+    pub name: String,
+    /// Fret numbers and note heads
+    pub fret: Style,
+    /// Slides, bends, hammer-ons and the effect lanes
+    pub technique: Style,
+    /// Strings, bar lines and staff lines
+    pub bar_line: Style,
+    pub selection: Style,
+    /// Notes of the highlighted voice
+    pub highlight: Style,
+    pub marker: Style,
+    /// Measures that do not fill their time signature
+    pub warning: Style,
+    /// Status bar and footer
+    pub status: Style,
+    /// When false, box drawing and other glyphs are replaced with ASCII and combining marks are
+    /// left out
+    pub unicode: bool,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme {
+            name: String::from("default"),
+            fret: Style::new(),
+            technique: Style::new(),
+            bar_line: Style::new(),
+            selection: Style::new().bg(Color::White).fg(Color::Black),
+            highlight: Style::new().fg(Color::Cyan),
+            marker: Style::new().bold(),
+            warning: Style::new().fg(Color::Red),
+            status: Style::new().reverse(),
+            unicode: true,
+        }
+    }
+}
+
+impl Theme {
+    pub fn by_name(name: &str) -> Option<Self> {
+        let default = Theme::default();
+        Some(match name {
+            "default" => default,
+            "dark" => Theme {
+                name: String::from(name),
+                fret: Style::new().white().bright().bold(),
+                technique: Style::new().yellow(),
+                bar_line: Style::new().blue(),
+                selection: Style::new().on_yellow().black(),
+                highlight: Style::new().cyan().bright(),
+                marker: Style::new().magenta().bold(),
+                warning: Style::new().red().bright(),
+                status: Style::new().on_blue().white(),
+                ..default
+            },
+            "light" => Theme {
+                name: String::from(name),
+                fret: Style::new().black().bold(),
+                technique: Style::new().blue(),
+                bar_line: Style::new().black().bright(),
+                selection: Style::new().on_blue().white(),
+                highlight: Style::new().magenta(),
+                marker: Style::new().red().bold(),
+                warning: Style::new().red(),
+                status: Style::new().on_white().black(),
+                ..default
+            },
+            // No color, only attributes that screen readers and old terminals ignore
+            "plain" => Theme {
+                name: String::from(name),
+                fret: Style::new(),
+                technique: Style::new(),
+                bar_line: Style::new(),
+                selection: Style::new().reverse(),
+                highlight: Style::new().underlined(),
+                marker: Style::new(),
+                warning: Style::new().bold(),
+                status: Style::new().reverse(),
+                unicode: false,
+            },
+            _ => return None,
+        })
+    }
+
+    /// The built-in theme after this one.
+    pub fn next(&self) -> Self {
+        let position = THEMES.iter().position(|name| *name == self.name);
+        let next = position.map_or(0, |position| (position + 1) % THEMES.len());
+        Theme::by_name(THEMES[next]).unwrap_or_default()
+    }
+
+    /// Sets the style of a part from a dotted string like `"yellow.bold"` or `"white.on_blue"`.
+    pub fn set(&mut self, part: &str, value: &str) -> Result<(), String> {
+        if part == "unicode" {
+            self.unicode = match value {
+                "true" => true,
+                "false" => false,
+                _ => return Err(format!("unicode must be true or false, not {}", value)),
+            };
+            return Ok(());
+        }
+        let style = Style::from_dotted_str(value);
+        match part {
+            "fret" => self.fret = style,
+            "technique" => self.technique = style,
+            "bar_line" => self.bar_line = style,
+            "selection" => self.selection = style,
+            "highlight" => self.highlight = style,
+            "marker" => self.marker = style,
+            "warning" => self.warning = style,
+            "status" => self.status = style,
+            _ => return Err(format!("unknown theme part {}", part)),
+        }
+        Ok(())
+    }
+
+    pub fn paint(style: &Style, text: impl Display) -> String {
+        style.apply_to(text).to_string()
+    }
+
+    /// Style of the cells with `role`, `None` for plain text.
+    pub fn style(&self, role: Role) -> Option<&Style> {
+        match role {
+            Role::Text => None,
+            Role::Fret => Some(&self.fret),
+            Role::Technique => Some(&self.technique),
+            Role::BarLine => Some(&self.bar_line),
+            Role::Selection => Some(&self.selection),
+            Role::Highlight => Some(&self.highlight),
+            Role::Marker => Some(&self.marker),
+            Role::Warning => Some(&self.warning),
+        }
+    }
+
+    /// A row of layout cells with the style of each run of cells applied.
+    pub fn paint_row(&self, row: &[Cell]) -> String {
+        layout::runs(row)
+            .into_iter()
+            .map(|(role, text)| match self.style(role) {
+                Some(style) => Theme::paint(style, text),
+                None => text,
+            })
+            .collect()
+    }
+
+    /// Text as it is drawn with this theme, with Unicode glyphs replaced in plain mode.
+    pub fn text(&self, text: &str) -> String {
+        if self.unicode {
+            return text.to_string();
+        }
+        layout::plain_text(text)
+    }
+}
@@ -0,0 +1,52 @@
+//! Songs of `resources/` and the golden files of `tests/golden/` shared by the tests.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use guitarpro::gp::Song;
+
+pub const MIKASA: &str = "Veil Of Maya-Mikasa.gp5";
+
+pub fn read_song(name: &str) -> Song {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("resources")
+        .join(name);
+    let data = fs::read(&path).unwrap_or_else(|err| panic!("{}: {}", path.display(), err));
+    let mut song = Song::default();
+    song.read_gp5(&data);
+    song
+}
+
+/// Compares `actual` with `tests/golden/<file_name>`, or writes it there with `UPDATE_GOLDEN`.
+pub fn check(file_name: &str, actual: &str) {
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests")
+        .join("golden")
+        .join(file_name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, actual).unwrap();
+        return;
+    }
+    let expected = fs::read_to_string(&path).unwrap_or_else(|err| {
+        panic!(
+            "{}: {}, run with UPDATE_GOLDEN=1 to write it",
+            path.display(),
+            err
+        )
+    });
+    if actual != expected {
+        let line_num = actual
+            .lines()
+            .zip(expected.lines())
+            .position(|(actual, expected)| actual != expected)
+            .unwrap_or_else(|| actual.lines().count().min(expected.lines().count()));
+        panic!(
+            "{} differs from what is made at line {}:\n  expected: {:?}\n    actual: {:?}",
+            path.display(),
+            line_num + 1,
+            expected.lines().nth(line_num).unwrap_or(""),
+            actual.lines().nth(line_num).unwrap_or("")
+        );
+    }
+}
@@ -0,0 +1,212 @@
+//! Golden tests and checks of the files exported for the songs in `resources/`. Run them with
+//! `UPDATE_GOLDEN=1` to write the expected files again after a change and review the diff.
+
+mod common;
+
+use guitarpro::gp::Song;
+use rstabs::export::{self, pdf, svg, Page};
+use rstabs::layout::{roadmap, LayoutOptions, TabLayout};
+
+use common::{check, read_song, MIKASA};
+
+/// The layout printed by the exports, with the rhythm under the strings.
+fn print_layout(song: &Song, track_num: usize) -> TabLayout<'_> {
+    TabLayout {
+        options: LayoutOptions {
+            show_notation: false,
+            ..LayoutOptions::default()
+        },
+        ..TabLayout::new(&song.tracks[track_num], &song.measure_headers)
+    }
+}
+
+#[test]
+fn svg_pages() {
+    let song = read_song(MIKASA);
+    let layout = print_layout(&song, 0);
+    let page = Page::default();
+    let pages = export::pages(&layout, 0..song.measure_headers.len(), &page);
+    assert_eq!(pages.len(), 20);
+    check("mikasa_page1.svg", &svg::document(&page, &pages[0]));
+}
+
+/// Position of the first `needle` in `data` from `from` on.
+fn find(data: &[u8], needle: &str, from: usize) -> Option<usize> {
+    data[from..]
+        .windows(needle.len())
+        .position(|window| window == needle.as_bytes())
+        .map(|at| from + at)
+}
+
+/// The number written in `data` at `at`.
+fn number_at(data: &[u8], at: usize) -> usize {
+    let digits: String = data[at..]
+        .iter()
+        .map(|byte| *byte as char)
+        .take_while(|ch| ch.is_ascii_digit())
+        .collect();
+    digits.parse().unwrap()
+}
+
+#[test]
+fn pdf_offsets_and_lengths() {
+    let song = read_song(MIKASA);
+    let track_nums = [0, 6];
+    let tracks: Vec<pdf::TrackPages> = track_nums
+        .iter()
+        .enumerate()
+        .map(|(num, track_num)| {
+            let title = if num == 0 { pdf::TITLE_HEIGHT } else { 0.0 };
+            let page = Page {
+                header: pdf::HEADER_HEIGHT,
+                title: title + pdf::TRACK_HEIGHT,
+                ..Page::default()
+            };
+            pdf::TrackPages {
+                name: song.tracks[*track_num].name.clone(),
+                pages: export::pages(
+                    &print_layout(&song, *track_num),
+                    0..song.measure_headers.len(),
+                    &page,
+                ),
+            }
+        })
+        .collect();
+    let page_count: usize = tracks.iter().map(|track| track.pages.len()).sum();
+    let titles = pdf::Titles {
+        title: song.name.clone(),
+        subtitle: song.artist.clone(),
+    };
+    let file = pdf::document(&titles, &tracks, &Page::default());
+    assert!(file.starts_with(b"%PDF-1.4\n"));
+    assert!(file.ends_with(b"%%EOF\n"));
+
+    // The table of every object, found from the offset at the end of the file
+    let startxref = find(&file, "startxref\n", 0).unwrap();
+    let xref = number_at(&file, startxref + "startxref\n".len());
+    assert_eq!(find(&file, "xref\n", xref), Some(xref));
+    let size = number_at(&file, xref + "xref\n0 ".len());
+    // Catalog, page tree, two fonts, and the content and the page object of every page
+    assert_eq!(size, 1 + 4 + 2 * page_count);
+    assert_eq!(
+        find(&file, "trailer\n", xref).map(|at| number_at(&file, at + "trailer\n<< /Size ".len())),
+        Some(size)
+    );
+    let entries = find(&file, "0000000000 65535 f \n", xref).unwrap() + 20;
+    for object_num in 1..size {
+        let entry = &file[entries + 20 * (object_num - 1)..entries + 20 * object_num];
+        assert!(entry.ends_with(b" 00000 n \n"), "{:?}", entry);
+        let offset = number_at(entry, 0);
+        let object = format!("{} 0 obj\n", object_num);
+        assert_eq!(
+            find(&file, &object, offset),
+            Some(offset),
+            "object {}",
+            object_num
+        );
+    }
+
+    // Every content stream is as long as it says
+    let mut pages = 0;
+    let mut at = 0;
+    while let Some(length) = find(&file, "<< /Length ", at) {
+        let stream = find(&file, ">>\nstream\n", length).unwrap() + ">>\nstream\n".len();
+        let end = stream + number_at(&file, length + "<< /Length ".len());
+        assert_eq!(find(&file, "endstream", stream), Some(end));
+        pages += 1;
+        at = end;
+    }
+    assert_eq!(pages, page_count);
+    let count = format!("/Count {} >>", page_count);
+    assert!(find(&file, &count, 0).is_some());
+}
+
+#[test]
+fn lilypond_score() {
+    let song = read_song(MIKASA);
+    // A guitar, the bass in bass clef, and the drums that are left out
+    check("mikasa.ly", &export::lilypond::document(&song, &[0, 4, 6]));
+}
+
+#[test]
+fn html_timing() {
+    let song = read_song(MIKASA);
+    let layout = print_layout(&song, 0);
+    let page = Page::default();
+    let html = export::html::document("Mikasa", &layout, song.tempo, &page);
+    let beats = html
+        .split_once("const BEATS = [\n")
+        .and_then(|(_, rest)| rest.split_once("];"))
+        .unwrap()
+        .0;
+    let beats: Vec<Vec<f32>> = beats
+        .lines()
+        .map(|line| {
+            let line = line.trim().trim_start_matches('[').trim_end_matches("],");
+            line.split(", ")
+                .map(|value| value.parse().unwrap())
+                .collect()
+        })
+        .collect();
+    let total: f64 = html
+        .split_once("const TOTAL = ")
+        .and_then(|(_, rest)| rest.split_once(';'))
+        .unwrap()
+        .0
+        .parse()
+        .unwrap();
+
+    // A beat for every beat played, in play order
+    let roadmap = roadmap::roadmap(&song.measure_headers);
+    let played: usize = roadmap
+        .iter()
+        .map(|measure_num| layout.measure_beats(*measure_num).beats.len())
+        .sum();
+    assert_eq!(beats.len(), played);
+    assert_eq!(beats[0][0], 0.0);
+    assert!(beats.windows(2).all(|pair| pair[0][0] < pair[1][0]));
+    // The first measure has two rests, four quarters at 146 bpm are 1643.8 ms
+    assert_eq!(beats[2][0], 1643.0);
+    let quarter = 60_000.0 / song.tempo as f64;
+    let expected: f64 = roadmap
+        .iter()
+        .map(|measure_num| {
+            let time = &song.measure_headers[*measure_num].time_signature;
+            quarter * time.numerator as f64 * 4.0 / time.denominator.value as f64
+        })
+        .sum();
+    assert!(
+        (expected - total).abs() < 1.0,
+        "{} ms for {} ms",
+        total,
+        expected
+    );
+
+    // Every beat is highlighted where it is drawn
+    let places = export::beat_places(&layout, 0..song.measure_headers.len(), &page);
+    let boxes: Vec<Vec<f32>> = roadmap
+        .iter()
+        .flat_map(|measure_num| {
+            places
+                .iter()
+                .filter(move |place| place.measure == *measure_num)
+                .map(|place| {
+                    vec![
+                        place.page as f32,
+                        place.at.0,
+                        place.at.1,
+                        place.size.0,
+                        place.size.1,
+                    ]
+                })
+        })
+        .collect();
+    let drawn: Vec<&[f32]> = beats.iter().map(|beat| &beat[1..]).collect();
+    assert_eq!(drawn, boxes);
+    // The boxes of a measure cover its beats side by side, a click falls in one of them
+    for pair in places.windows(2) {
+        if pair[0].measure == pair[1].measure {
+            assert_eq!(pair[0].at.0 + pair[0].size.0, pair[1].at.0);
+        }
+    }
+}
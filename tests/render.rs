@@ -0,0 +1,305 @@
+//! Golden tests of what is drawn for the files in `resources/`. After a change to the drawing,
+//! run them with `UPDATE_GOLDEN=1` to write the expected files again and review the diff.
+
+mod common;
+
+use guitarpro::enums::{NoteType, SlideType};
+use guitarpro::gp::Song;
+use guitarpro::headers::Marker;
+use rstabs::layout::{plain_text, Cursor, LayoutOptions, MeasureLayout, Role, TabLayout};
+use rstabs::tabsprint::{BeatEdit, Direction, Effect, Mouse, TabsPrint, Terminal, Theme};
+
+use common::{check, read_song, MIKASA};
+
+/// A plain terminal without notation, editing the first note of `song`.
+fn edit_first_note(song: Song) -> Terminal {
+    let mut terminal = Terminal::headless(24, 80);
+    terminal.set_song(song);
+    terminal.set_theme(Theme::by_name("plain").unwrap());
+    terminal.toggle_notation();
+    terminal.toggle_edit();
+    terminal.cursor_move(Direction::NextNote);
+    terminal
+}
+
+#[test]
+fn first_track() {
+    let song = read_song(MIKASA);
+    let layout = TabLayout::new(&song.tracks[0], &song.measure_headers);
+    check("mikasa_track1.txt", &layout.render(0..32).to_string());
+}
+
+#[test]
+fn every_track() {
+    let song = read_song(MIKASA);
+    let mut text = String::new();
+    for track in &song.tracks {
+        let layout = TabLayout::new(track, &song.measure_headers);
+        text.push_str(&format!("{}\n", track.name));
+        text.push_str(&layout.render(0..4.min(track.measures.len())).to_string());
+    }
+    check("mikasa_tracks.txt", &text);
+}
+
+#[test]
+fn bar_lines_frame_the_strings_of_every_track() {
+    let song = read_song(MIKASA);
+    for track in &song.tracks {
+        let layout = TabLayout::new(track, &song.measure_headers);
+        for measure in layout.measures(0..4) {
+            let bar_line: String = measure.grid.rows[measure.strings.clone()]
+                .iter()
+                .map(|row| row[0].glyph.as_str())
+                .collect();
+            let inner = "├".repeat(measure.strings.len() - 2);
+            assert_eq!(bar_line, format!("╭{}╰", inner), "{}", track.name);
+        }
+    }
+}
+
+#[test]
+fn repeats_endings_and_markers() {
+    // The file has none, they are added to its first measures
+    let mut song = read_song(MIKASA);
+    let headers = &mut song.measure_headers;
+    headers[1].repeat_open = true;
+    headers[1].marker = Some(Marker {
+        title: String::from("Verse"),
+        ..Marker::default()
+    });
+    headers[2].repeat_alternative = 0b1;
+    headers[2].repeat_close = 1;
+    headers[3].repeat_alternative = 0b10;
+    let mut text = String::new();
+    // Six strings and the seven lines of the drums
+    for track in [&song.tracks[0], &song.tracks[6]] {
+        let layout = TabLayout {
+            options: LayoutOptions {
+                show_notation: false,
+                ..LayoutOptions::default()
+            },
+            ..TabLayout::new(track, &song.measure_headers)
+        };
+        text.push_str(&layout.render(0..5).to_string());
+    }
+    check("mikasa_repeats.txt", &text);
+}
+
+#[test]
+fn plain_text_without_notation() {
+    let song = read_song(MIKASA);
+    let layout = TabLayout {
+        options: LayoutOptions {
+            show_notation: false,
+            unicode: false,
+            ..LayoutOptions::default()
+        },
+        ..TabLayout::new(&song.tracks[0], &song.measure_headers)
+    };
+    check("mikasa_plain.txt", &layout.render(0..16).to_string());
+}
+
+#[test]
+fn cursor_selects_the_string_in_edit_mode() {
+    let song = read_song(MIKASA);
+    let cursor = Cursor {
+        measure: 1,
+        beat: 2,
+        string: 3,
+        playing: false,
+    };
+    let layout = TabLayout {
+        options: LayoutOptions {
+            edit_mode: true,
+            ..LayoutOptions::default()
+        },
+        cursor: Some(cursor),
+        ..TabLayout::new(&song.tracks[0], &song.measure_headers)
+    };
+    let measure = &layout.measures(1..2)[0];
+    let column = MeasureLayout::fret_column(cursor.beat);
+    for (string_num, row) in measure.grid.rows[measure.strings.clone()]
+        .iter()
+        .enumerate()
+    {
+        assert_eq!(
+            row[column].role == Role::Selection,
+            string_num == cursor.string,
+            "string {}",
+            string_num
+        );
+    }
+}
+
+#[test]
+fn terminal_screen() {
+    let mut terminal = Terminal::headless(24, 80);
+    terminal.set_song(read_song(MIKASA));
+    let mut text = terminal.screen();
+    terminal.cursor_move(Direction::PageDown);
+    terminal.cursor_move(Direction::NextNote);
+    terminal.toggle_edit();
+    terminal.set_theme(Theme::by_name("plain").unwrap());
+    text.push_str(&terminal.screen());
+    check("mikasa_screen.txt", &text);
+}
+
+#[test]
+fn effects_toggle_on_the_note_under_the_cursor() {
+    let mut terminal = edit_first_note(read_song(MIKASA));
+    terminal.cursor_move(Direction::Down);
+    let before = terminal.screen();
+    terminal.toggle_effect(Effect::PalmMute);
+    let palm_muted = terminal.screen();
+    assert!(
+        palm_muted.contains("  P.M.|       P.M.---"),
+        "{}",
+        palm_muted
+    );
+    terminal.toggle_effect(Effect::PalmMute);
+    assert_eq!(terminal.screen(), before);
+
+    // Nothing to change without a note on the string
+    terminal.cursor_move(Direction::Up);
+    let before = terminal.screen();
+    terminal.toggle_effect(Effect::DeadNote);
+    assert_eq!(terminal.screen(), before);
+}
+
+#[test]
+fn beat_edits_mark_measures_that_do_not_fill_their_time() {
+    let mut terminal = edit_first_note(read_song(MIKASA));
+    let before = terminal.screen();
+    terminal.edit_beat(BeatEdit::Longer);
+    assert!(terminal.screen().contains("\n2 (too long)"));
+    terminal.edit_beat(BeatEdit::Shorter);
+    assert_eq!(terminal.screen(), before);
+    terminal.edit_beat(BeatEdit::Shorter);
+    assert!(terminal.screen().contains("\n2 (too short)"));
+
+    // A rest keeps the duration of the notes
+    terminal.edit_beat(BeatEdit::Longer);
+    terminal.edit_beat(BeatEdit::Rest);
+    let rest = terminal.screen();
+    assert!(rest.contains("\n2\n"));
+    assert!(rest.contains("\n|--------6---"));
+}
+
+#[test]
+fn lyrics_move_to_the_beats_left_sung_after_an_edit() {
+    let mut song = read_song(MIKASA);
+    song.lyrics.track_choice = 1;
+    song.lyrics.lines = vec![(0, 2, String::from("one two three"))];
+    let mut terminal = edit_first_note(song);
+    assert!(terminal.screen().contains("\n   one   two   three\n"));
+    terminal.edit_beat(BeatEdit::Rest);
+    assert!(terminal.screen().contains("\n         one   two   three\n"));
+
+    // A beat with all its notes tied is held, not sung
+    terminal.cursor_move(Direction::NextNote);
+    terminal.cursor_move(Direction::NextNote);
+    for _ in 0..4 {
+        terminal.cursor_move(Direction::Down);
+    }
+    terminal.toggle_effect(Effect::Tie);
+    assert!(terminal.screen().contains("\n         one   two   three\n"));
+    terminal.cursor_move(Direction::Down);
+    terminal.toggle_effect(Effect::Tie);
+    assert!(terminal
+        .screen()
+        .contains("\n         one         two   three\n"));
+}
+
+#[test]
+fn click_moves_the_cursor_to_the_beat_and_string() {
+    let mut terminal = Terminal::headless(24, 80);
+    terminal.set_song(read_song(MIKASA));
+    terminal.set_theme(Theme::by_name("plain").unwrap());
+    terminal.toggle_notation();
+    // The second string of the second measure, under its status line, title, chords and lane
+    terminal.mouse(Mouse::Click {
+        column: MeasureLayout::fret_column(3),
+        row: 12,
+    });
+    let screen = terminal.screen();
+    assert!(screen.contains("measure 2/113 beat 4/12"), "{}", screen);
+    // The bar line belongs to the first beat, the last beat takes the end of the line
+    for (column, beat) in [(0, 1), (MeasureLayout::beat_column(1) - 1, 1), (79, 12)] {
+        terminal.mouse(Mouse::Click { column, row: 12 });
+        let status = format!("measure 2/113 beat {}/12", beat);
+        assert!(terminal.screen().contains(&status), "column {}", column);
+    }
+}
+
+#[test]
+fn status_bar_shows_the_linear_and_loop_modes() {
+    let mut terminal = Terminal::headless(24, 80);
+    terminal.set_song(read_song(MIKASA));
+    let status = |terminal: &Terminal| {
+        let screen = terminal.screen();
+        screen
+            .lines()
+            .find(|line| line.contains(" VIEW │ measure "))
+            .unwrap_or_else(|| panic!("no status bar in\n{}", screen))
+            .to_string()
+    };
+    terminal.toggle_linear();
+    assert!(status(&terminal).contains("│ linear"));
+    terminal.toggle_loop();
+    assert!(status(&terminal).contains("│ linear │ loop"));
+    terminal.toggle_linear();
+    terminal.toggle_loop();
+    assert!(!status(&terminal).contains("linear"));
+    assert!(!status(&terminal).contains("loop"));
+}
+
+#[test]
+fn tied_notes_hold_the_fret_of_the_previous_note() {
+    let mut terminal = edit_first_note(read_song(MIKASA));
+    terminal.cursor_move(Direction::NextNote);
+    terminal.cursor_move(Direction::NextNote);
+    for _ in 0..4 {
+        terminal.cursor_move(Direction::Down);
+    }
+    let fret = |terminal: &Terminal, measure: usize, beat: usize, string: i8| {
+        let note = terminal.tab[0].measures[measure].voices[0].beats[beat]
+            .notes
+            .iter()
+            .find(|note| note.string == string)
+            .unwrap();
+        (note.kind.clone(), note.value)
+    };
+    assert_eq!(fret(&terminal, 1, 2, 5), (NoteType::Normal, 0));
+    terminal.toggle_effect(Effect::Tie);
+    assert_eq!(fret(&terminal, 1, 2, 5), (NoteType::Tie, 8));
+    terminal.toggle_effect(Effect::Tie);
+    assert_eq!(fret(&terminal, 1, 2, 5).0, NoteType::Normal);
+
+    // Back over the bar line to the last note on the string
+    for _ in 0..22 {
+        terminal.cursor_move(Direction::Right);
+    }
+    for _ in 0..3 {
+        terminal.cursor_move(Direction::Up);
+    }
+    assert!(terminal.screen().contains("measure 4/113 beat 1/12"));
+    assert_eq!(fret(&terminal, 3, 0, 2), (NoteType::Normal, 10));
+    terminal.toggle_effect(Effect::Tie);
+    assert_eq!(fret(&terminal, 3, 0, 2), (NoteType::Tie, 8));
+}
+
+#[test]
+fn slide_out_of_a_hammered_note_keeps_its_glyph() {
+    let mut song = read_song(MIKASA);
+    let effect = &mut song.tracks[0].measures[1].voices[0].beats[0]
+        .notes
+        .iter_mut()
+        .find(|note| note.string == 2)
+        .unwrap()
+        .effect;
+    effect.hammer = true;
+    effect.slides = vec![SlideType::ShiftSlideTo];
+    let layout = TabLayout::new(&song.tracks[0], &song.measure_headers);
+    let text = plain_text(&layout.render(1..2).to_string());
+    assert!(text.contains("\n|--6-h/--6-----"), "{}", text);
+}
@@ -0,0 +1,77 @@
+//! Play order of repeats and alternate endings.
+
+use guitarpro::headers::MeasureHeader;
+use rstabs::layout::roadmap::{alternative_label, roadmap};
+
+/// Headers from a bar per measure: `|:` opens a repeat, `:|` closes it once, `:|3` plays the
+/// section 3 more times and `1.` or `1.2.` starts an alternate ending.
+fn headers(bars: &[&str]) -> Vec<MeasureHeader> {
+    bars.iter()
+        .map(|bar| {
+            let mut header = MeasureHeader {
+                repeat_open: bar.contains("|:"),
+                ..MeasureHeader::default()
+            };
+            if let Some((_, times)) = bar.split_once(":|") {
+                header.repeat_close = times.parse().unwrap_or(1);
+            }
+            // Every part before a '.' ends with the number of an ending
+            let parts: Vec<&str> = bar.split('.').collect();
+            for part in &parts[..parts.len() - 1] {
+                if let Some(ending) = part.chars().last().and_then(|ch| ch.to_digit(10)) {
+                    header.repeat_alternative |= 1 << (ending - 1);
+                }
+            }
+            header
+        })
+        .collect()
+}
+
+#[test]
+fn no_repeats() {
+    assert_eq!(roadmap(&headers(&["", "", ""])), [0, 1, 2]);
+}
+
+#[test]
+fn repeat_from_the_start_without_an_open_sign() {
+    assert_eq!(roadmap(&headers(&["", ":|", ""])), [0, 1, 0, 1, 2]);
+}
+
+#[test]
+fn repeat_several_times() {
+    assert_eq!(
+        roadmap(&headers(&["", "|:", ":|2", ""])),
+        [0, 1, 2, 1, 2, 1, 2, 3]
+    );
+}
+
+#[test]
+fn alternate_endings() {
+    assert_eq!(
+        roadmap(&headers(&["|:", "", "1. :|", "2.", ""])),
+        [0, 1, 2, 0, 1, 3, 4]
+    );
+}
+
+#[test]
+fn ending_shared_by_two_passes() {
+    assert_eq!(
+        roadmap(&headers(&["|:", "1.2. :|2", "3."])),
+        [0, 1, 0, 1, 0, 2]
+    );
+}
+
+#[test]
+fn repeat_after_alternate_endings() {
+    // The section after the endings repeats from its start
+    assert_eq!(
+        roadmap(&headers(&["|:", "", "1. :|", "2.", "", ":|"])),
+        [0, 1, 2, 0, 1, 3, 4, 5, 4, 5]
+    );
+}
+
+#[test]
+fn ending_labels() {
+    assert_eq!(alternative_label(0b1), "1.");
+    assert_eq!(alternative_label(0b101), "1.3.");
+}